@@ -1,6 +1,11 @@
 mod dname;
+mod edns;
+#[cfg(test)]
+pub(crate) mod fixtures;
 mod header;
 mod label;
 mod message;
+mod message_ref;
 mod question;
+mod rdata;
 mod record;
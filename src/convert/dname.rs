@@ -1,84 +1,241 @@
-use std::io::{Cursor, Seek, SeekFrom};
+use std::io::Cursor;
 
-use byteorder::ReadBytesExt;
+use crate::{encoder::Encoder, types::dname::*};
 
-use crate::types::dname::*;
+impl std::str::FromStr for DomainName {
+    type Err = Error;
 
-impl From<String> for DomainName {
-    fn from(value: String) -> Self {
-        Self(
-            value
-                .split('.')
-                .map(|substr| Label::new(substr.to_string()))
-                .collect(),
-        )
+    /// Parses a [`DomainName`] from its presentation form, as described in RFC 1035 section 5.1
+    ///
+    /// Within a label, `\X` stands for the character X (e.g. `\.` for a dot that does not separate labels),
+    /// and `\DDD` for the octet with decimal value DDD.
+    /// A trailing dot marks the name as absolute, and both "" and "." are the root.
+    fn from_str(domain_name: &str) -> Result<Self> {
+        if domain_name.is_empty() || domain_name == "." {
+            return Ok(Self::root());
+        }
+
+        let mut labels = Vec::new();
+        let mut current = Vec::new();
+        let mut chars = domain_name.bytes();
+        let invalid_escape = || Error::InvalidEscape(domain_name.to_string());
+
+        while let Some(byte) = chars.next() {
+            match byte {
+                b'.' if current.is_empty() => {
+                    return Err(Error::EmptyLabel(domain_name.to_string()))
+                }
+                b'.' => labels.push(Label::new(std::mem::take(&mut current))),
+                b'\\' => match chars.next() {
+                    Some(digit) if digit.is_ascii_digit() => {
+                        let digits = [
+                            digit,
+                            chars.next().ok_or_else(invalid_escape)?,
+                            chars.next().ok_or_else(invalid_escape)?,
+                        ];
+                        if !digits.iter().all(u8::is_ascii_digit) {
+                            return Err(invalid_escape());
+                        }
+                        let value = digits
+                            .iter()
+                            .fold(0u16, |acc, digit| acc * 10 + (digit - b'0') as u16);
+                        current.push(u8::try_from(value).map_err(|_| invalid_escape())?);
+                    }
+                    Some(escaped) => current.push(escaped),
+                    None => return Err(invalid_escape()),
+                },
+                _ => current.push(byte),
+            }
+        }
+        // a trailing separator marks the name as absolute
+        let absolute = current.is_empty();
+        if !absolute {
+            labels.push(Label::new(current));
+        }
+
+        Self::checked(labels, absolute)
     }
 }
 
 impl From<DomainName> for String {
     fn from(value: DomainName) -> Self {
-        value
-            .0
-            .into_iter()
-            .map(|label| label.to_string())
-            .reduce(|acc, label_str| acc + "." + &label_str)
-            .unwrap_or_default()
+        value.to_string()
     }
 }
 
 impl DomainName {
-    /// Converts a [`DomainName`] to owned bytes
+    /// Converts a [`DomainName`] to owned bytes, without compression
     pub fn into_bytes(self) -> Vec<u8> {
-        let mut val: Vec<u8> = self.0.into_iter().flat_map(Label::into_bytes).collect();
+        let mut encoder = Encoder::uncompressed();
+        self.encode(&mut encoder);
+        encoder.into_bytes()
+    }
+
+    /// Writes a [`DomainName`] to an [`Encoder`]
+    ///
+    /// If the encoder compresses, the longest suffix that was already written
+    /// is replaced by a pointer to its earlier occurrence.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        let keys: Vec<Vec<u8>> = self
+            .labels
+            .iter()
+            .map(|label| label.0.to_ascii_lowercase())
+            .collect();
+
+        for (idx, label) in self.labels.iter().enumerate() {
+            let suffix = &keys[idx..];
+            if let Some(offset) = encoder.find_name(suffix) {
+                encoder.write_u16(Self::POINTER_MASK | offset);
+                return;
+            }
+            encoder.remember_name(suffix.to_vec(), encoder.position());
+            encoder.write_bytes(&label.clone().into_bytes());
+        }
+
         // name bytes have zero octet delimiter
-        val.push(Self::TERMINATOR);
-        val
+        encoder.write_u8(Self::TERMINATOR);
     }
 
     /// Reads a [`DomainName`] from a slice of bytes
+    ///
+    /// Compression pointers are followed, but only if they point strictly before
+    /// the labels that contain them, which rules out pointer loops.
+    /// The cursor is left right after the name's terminator or first pointer.
     pub fn from_bytes(bytes: &mut Cursor<&[u8]>) -> Result<Self> {
-        // buffers and metadata storage
+        let mut wire_labels = WireLabels::new(bytes.get_ref(), bytes.position() as usize);
 
-        let mut label_bytes_buffer = [0u8; Label::MAX_LABEL_SIZE];
-        let mut labels = Vec::new();
+        let labels = wire_labels
+            .by_ref()
+            .map(|label| label.map(|label| Label::new(label.to_vec())))
+            .collect::<Result<Vec<Label>>>()?;
 
+        if let Some(end) = wire_labels.end() {
+            bytes.set_position(end as u64);
+        }
+
+        Ok(Self {
+            labels,
+            absolute: true,
+        })
+    }
+}
+
+/// Walks the labels of a name within a message without copying them, following compression pointers
+///
+/// Stops at the first error, which is returned as the last item.
+pub(crate) struct WireLabels<'a> {
+    msg: &'a [u8],
+    pos: usize,
+    /// pointers must point before the start of the labels currently being read
+    segment_start: usize,
+    hops: usize,
+    /// the sum of all label octets and label lengths read so far
+    name_size: usize,
+    /// the position right after the name's terminator or first pointer
+    end: Option<usize>,
+    done: bool,
+}
+
+impl<'a> WireLabels<'a> {
+    /// Starts walking the name found at `pos` within `msg`
+    pub(crate) fn new(msg: &'a [u8], pos: usize) -> Self {
+        Self {
+            msg,
+            pos,
+            segment_start: pos,
+            hops: 0,
+            name_size: 0,
+            end: None,
+            done: false,
+        }
+    }
+
+    /// The position right after the name, once the whole name has been walked
+    pub(crate) fn end(&self) -> Option<usize> {
+        self.end
+    }
+
+    fn read_u8(&self, pos: usize) -> Result<u8> {
+        self.msg
+            .get(pos)
+            .copied()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
+    }
+
+    fn next_label(&mut self) -> Result<Option<&'a [u8]>> {
         loop {
-            let size = bytes.read_u8()?;
+            let size = self.read_u8(self.pos)?;
 
             match size {
-                size if Self::is_compressed(size) => {
-                    // get pointed-to name
-                    let second = bytes.read_u8()?;
+                size if DomainName::is_compressed(size) => {
+                    let second = self.read_u8(self.pos + 1)?;
                     let name_pos = u16::from_be_bytes([size & 0b0011_1111, second]);
-                    labels.extend(DomainName::read_compressed_label(bytes, name_pos)?);
-                    break;
+
+                    self.hops += 1;
+                    if self.hops > DomainName::MAX_POINTER_HOPS {
+                        return Err(Error::PointerLimit { hops: self.hops });
+                    }
+                    if name_pos as usize >= self.segment_start {
+                        return Err(Error::ForwardPointer {
+                            pointer: name_pos,
+                            position: self.pos as u64,
+                        });
+                    }
+
+                    // get pointed-to labels
+                    self.end.get_or_insert(self.pos + 2);
+                    self.segment_start = name_pos as usize;
+                    self.pos = name_pos as usize;
+                }
+                DomainName::TERMINATOR => {
+                    self.end.get_or_insert(self.pos + 1);
+                    return Ok(None);
                 }
-                Self::TERMINATOR => {
-                    break;
+                size if size as usize > Label::MAX_LABEL_SIZE => {
+                    return Err(Error::LabelTooLong {
+                        size: size as usize,
+                    });
                 }
                 _ => {
-                    let dest = &mut label_bytes_buffer[..size as usize];
-                    let label = Label::read_label(bytes, dest)
-                        .map_err(|source| Error::Label { size, source })?;
-                    labels.push(label);
+                    self.name_size += 1 + size as usize;
+                    // account for the terminator
+                    if self.name_size + 1 > DomainName::MAX_NAME_SIZE {
+                        return Err(Error::NameTooLong {
+                            size: self.name_size + 1,
+                        });
+                    }
+
+                    let start = self.pos + 1;
+                    let label =
+                        self.msg
+                            .get(start..start + size as usize)
+                            .ok_or_else(|| Error::Label {
+                                size,
+                                source: label::Error::Io {
+                                    src_amt: self.msg.len().saturating_sub(start),
+                                    dest_amt: size as usize,
+                                    source: std::io::ErrorKind::UnexpectedEof.into(),
+                                },
+                            })?;
+                    self.pos = start + size as usize;
+                    return Ok(Some(label));
                 }
             }
         }
-
-        Ok(Self(labels))
     }
+}
 
-    fn read_compressed_label(bytes: &mut Cursor<&[u8]>, name_pos: u16) -> Result<Vec<Label>> {
-        // save current pos
-        let old_pos = bytes.position();
+impl<'a> Iterator for WireLabels<'a> {
+    type Item = Result<&'a [u8]>;
 
-        // get name
-        bytes.seek(SeekFrom::Start(name_pos as u64))?;
-        let name = Self::from_bytes(bytes)?;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
 
-        // reset to current pos
-        bytes.seek(SeekFrom::Start(old_pos))?;
-        Ok(name.0)
+        let label = self.next_label().transpose();
+        self.done = !matches!(label, Some(Ok(_)));
+        label
     }
 }
 
@@ -97,6 +254,19 @@ mod tests {
         assert_eq!(result_bytes, correct_bytes);
     }
 
+    /// Tests that a repeated suffix is written as a pointer to its first occurrence
+    #[test]
+    fn encode_compressed_dname() {
+        let mut encoder = Encoder::new();
+        DomainName::new("www.google.com").encode(&mut encoder);
+        DomainName::new("mail.GOOGLE.com").encode(&mut encoder);
+        DomainName::new("www.google.com").encode(&mut encoder);
+
+        let correct_bytes = b"\x03www\x06google\x03com\x00\x04mail\xc0\x04\xc0\x00";
+
+        assert_eq!(encoder.into_bytes(), correct_bytes);
+    }
+
     /// Tests decoding of "google.com"
     #[test]
     fn decode_dname() -> Result<()> {
@@ -109,4 +279,246 @@ mod tests {
 
         Ok(())
     }
+
+    /// Tests decoding of a name that points back to an earlier name
+    #[test]
+    fn decode_compressed_dname() -> Result<()> {
+        let mut bytes = Cursor::new(&b"\x06google\x03com\x00\x04mail\xc0\x00\xff"[..]);
+        bytes.set_position(12);
+
+        let result_dname = DomainName::from_bytes(&mut bytes)?;
+
+        assert_eq!(result_dname, DomainName::new("mail.google.com"));
+        assert_eq!(bytes.position(), 19);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_self_pointer() {
+        let mut bytes = Cursor::new(&b"\x04mail\xc0\x00"[..]);
+
+        let result = DomainName::from_bytes(&mut bytes);
+
+        assert!(matches!(
+            result,
+            Err(Error::ForwardPointer {
+                pointer: 0,
+                position: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_forward_pointer() {
+        let mut bytes = Cursor::new(&b"\xc0\x02\x06google\x03com\x00"[..]);
+
+        let result = DomainName::from_bytes(&mut bytes);
+
+        assert!(matches!(result, Err(Error::ForwardPointer { .. })));
+    }
+
+    #[test]
+    fn decode_pointer_loop() {
+        // two names pointing at each other, entered through a pointer placed after both
+        let mut bytes = Cursor::new(&b"\x01a\xc0\x04\x01b\xc0\x00\xc0\x04"[..]);
+        bytes.set_position(8);
+
+        let result = DomainName::from_bytes(&mut bytes);
+
+        assert!(matches!(result, Err(Error::ForwardPointer { .. })));
+    }
+
+    #[test]
+    fn decode_pointer_chain() {
+        // a root name followed by 200 pointers, each pointing at the previous one
+        let mut bytes = vec![DomainName::TERMINATOR];
+        for idx in 0..200u16 {
+            let target = if idx == 0 { 0 } else { 1 + 2 * (idx - 1) };
+            bytes.extend((DomainName::POINTER_MASK | target).to_be_bytes());
+        }
+        let mut bytes = Cursor::new(&bytes[..]);
+        bytes.set_position(1 + 2 * 199);
+
+        let result = DomainName::from_bytes(&mut bytes);
+
+        assert!(matches!(result, Err(Error::PointerLimit { .. })));
+    }
+
+    #[test]
+    fn decode_long_dname() {
+        // 5 labels of 63 octets exceed the 255 octet limit
+        let mut bytes = [b"\x3f".as_slice(), &[b'a'; 63]].concat().repeat(5);
+        bytes.push(DomainName::TERMINATOR);
+
+        let result = DomainName::from_bytes(&mut Cursor::new(&bytes[..]));
+
+        assert!(matches!(result, Err(Error::NameTooLong { .. })));
+    }
+
+    #[test]
+    fn decode_long_label() {
+        let mut bytes = Cursor::new(&b"\x40aaaa\x00"[..]);
+
+        let result = DomainName::from_bytes(&mut bytes);
+
+        assert!(matches!(result, Err(Error::LabelTooLong { size: 0x40 })));
+    }
+
+    /// Tests that dots and binary octets within labels survive the presentation form
+    #[test]
+    fn escaped_dname_round_trip() -> Result<()> {
+        let bytes = b"\x07dots.in\x03\x00\xff\\\x07example\x00";
+
+        let result_dname = DomainName::from_bytes(&mut Cursor::new(&bytes[..]))?;
+        let presentation = result_dname.to_string();
+
+        assert_eq!(presentation, r"dots\.in.\000\255\\.example.");
+        assert_eq!(presentation.parse::<DomainName>()?, result_dname);
+        assert_eq!(result_dname.into_bytes(), bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_invalid_dname() {
+        for (input, expected) in [
+            ("example..com", "empty label"),
+            (".example.com", "empty label"),
+            (r"example\", "escape"),
+            (r"ex\25", "escape"),
+            (r"ex\256ample", "escape"),
+        ] {
+            let result = input.parse::<DomainName>();
+            assert!(
+                matches!(
+                    (&result, expected),
+                    (Err(Error::EmptyLabel(_)), "empty label")
+                        | (Err(Error::InvalidEscape(_)), "escape")
+                ),
+                "{input}: {result:?}"
+            );
+        }
+
+        let long_label = "a".repeat(Label::MAX_LABEL_SIZE + 1);
+        assert!(matches!(
+            long_label.parse::<DomainName>(),
+            Err(Error::LabelTooLong { size: 64 })
+        ));
+    }
+
+    #[test]
+    fn dname_case_insensitive() {
+        use std::collections::HashSet;
+
+        let lower = DomainName::new("example.com");
+        let mixed = DomainName::new("Example.COM");
+
+        assert_eq!(lower, mixed);
+        assert_eq!(lower.cmp(&mixed), std::cmp::Ordering::Equal);
+        assert_eq!(HashSet::from([lower, mixed.clone()]).len(), 1);
+        assert_eq!(mixed.to_string(), "Example.COM");
+    }
+
+    /// Tests the canonical ordering example of RFC 4034 section 6.1
+    #[test]
+    fn dname_canonical_order() {
+        let correct_order: Vec<DomainName> = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            r"zABC.a.EXAMPLE",
+            "z.example",
+            r"\001.z.example",
+            "*.z.example",
+            r"\200.z.example",
+        ]
+        .into_iter()
+        .map(DomainName::new)
+        .collect();
+
+        let mut result_order = correct_order.clone();
+        result_order.reverse();
+        result_order.sort();
+
+        assert_eq!(result_order, correct_order);
+    }
+
+    #[test]
+    fn dname_root() {
+        for root in ["", "."] {
+            let result_dname = DomainName::new(root);
+            assert!(result_dname.is_root());
+            assert_eq!(result_dname.num_labels(), 0);
+            assert_eq!(result_dname.to_string(), ".");
+            assert_eq!(result_dname.into_bytes(), [DomainName::TERMINATOR]);
+        }
+        assert_eq!(DomainName::root().parent(), None);
+    }
+
+    #[test]
+    fn dname_absolute() {
+        let absolute = DomainName::new("www.example.com.");
+        let relative = DomainName::new("www.example.com");
+
+        assert!(absolute.is_absolute());
+        assert!(!relative.is_absolute());
+        assert_eq!(absolute, relative);
+        assert_eq!(absolute.to_string(), "www.example.com.");
+        assert_eq!(relative.to_string(), "www.example.com");
+        assert_eq!(
+            relative.clone().into_absolute().to_string(),
+            "www.example.com."
+        );
+        assert_eq!(absolute.wire_len(), relative.into_bytes().len());
+    }
+
+    #[test]
+    fn dname_hierarchy() -> Result<()> {
+        let name = DomainName::new("www.Example.com.");
+
+        let ancestors: Vec<String> = std::iter::successors(Some(name.clone()), DomainName::parent)
+            .map(|ancestor| ancestor.to_string())
+            .collect();
+        assert_eq!(ancestors, ["www.Example.com.", "Example.com.", "com.", "."]);
+
+        assert!(name.is_subdomain_of(&DomainName::new("example.COM.")));
+        assert!(name.is_subdomain_of(&name));
+        assert!(name.is_subdomain_of(&DomainName::root()));
+        assert!(!name.is_subdomain_of(&DomainName::new("ample.com.")));
+        assert!(!DomainName::new("com.").is_subdomain_of(&name));
+        assert_eq!(DomainName::new("www").parent(), None);
+
+        let labels: Vec<&[u8]> = name.labels().map(Label::as_bytes).collect();
+        assert_eq!(labels, [&b"www"[..], b"Example", b"com"]);
+        Ok(())
+    }
+
+    #[test]
+    fn dname_append_prepend() -> Result<()> {
+        let zone = DomainName::new("example.com.");
+
+        let appended = DomainName::new("build01").append(&zone)?;
+        assert_eq!(appended.to_string(), "build01.example.com.");
+        assert!(appended.is_absolute());
+
+        let prepended = zone.prepend(Label::new(b"www".to_vec()))?;
+        assert_eq!(prepended.to_string(), "www.example.com.");
+
+        assert!(matches!(zone.append(&zone), Err(Error::Absolute(_))));
+        assert!(matches!(
+            zone.prepend(Label::new(vec![b'a'; 64])),
+            Err(Error::LabelTooLong { size: 64 })
+        ));
+        assert!(matches!(
+            zone.prepend(Label::new(vec![])),
+            Err(Error::EmptyLabel(_))
+        ));
+
+        let long_name = DomainName::new(&vec!["a".repeat(63); 3].join("."));
+        assert!(matches!(
+            long_name.append(&long_name.clone().into_absolute()),
+            Err(Error::NameTooLong { .. })
+        ));
+        Ok(())
+    }
 }
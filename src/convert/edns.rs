@@ -0,0 +1,229 @@
+use std::io::{Cursor, Read};
+
+use byteorder::{NetworkEndian, ReadBytesExt};
+
+use crate::{
+    dname::DomainName,
+    edns::{Edns, EdnsOption, Error, Result},
+    encoder::Encoder,
+    qclass::QClass,
+    qtype::QType,
+    rdata::RData,
+    record::Record,
+};
+
+impl EdnsOption {
+    /// Reads an [`EdnsOption`] from a slice of bytes
+    pub fn from_bytes(bytes: &mut Cursor<&[u8]>) -> Result<Self> {
+        let code = bytes.read_u16::<NetworkEndian>()?;
+        let length = bytes.read_u16::<NetworkEndian>()?;
+
+        let mut data = vec![0; length as usize];
+        bytes.read_exact(&mut data)?;
+        let invalid_length = || Error::OptionLength { code, length };
+
+        let option = match code {
+            Self::NSID => EdnsOption::Nsid(data),
+            Self::CLIENT_SUBNET => {
+                if data.len() < 4 {
+                    return Err(invalid_length());
+                }
+                EdnsOption::ClientSubnet {
+                    family: u16::from_be_bytes([data[0], data[1]]),
+                    source_prefix: data[2],
+                    scope_prefix: data[3],
+                    address: data[4..].to_vec(),
+                }
+            }
+            Self::COOKIE => {
+                // a client cookie, optionally followed by an 8 to 32 octet server cookie
+                if !matches!(data.len(), 8 | 16..=40) {
+                    return Err(invalid_length());
+                }
+                let (client, server) = data.split_at(8);
+                EdnsOption::Cookie {
+                    client: client.try_into().unwrap(),
+                    server: server.to_vec(),
+                }
+            }
+            Self::TCP_KEEPALIVE => match data[..] {
+                [] => EdnsOption::TcpKeepalive(None),
+                [higher, lower] => {
+                    EdnsOption::TcpKeepalive(Some(u16::from_be_bytes([higher, lower])))
+                }
+                _ => return Err(invalid_length()),
+            },
+            Self::PADDING => EdnsOption::Padding(length),
+            code => EdnsOption::Unknown { code, data },
+        };
+
+        Ok(option)
+    }
+
+    /// Writes an [`EdnsOption`] to an [`Encoder`]
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_u16(self.code());
+
+        let length_pos = encoder.position();
+        encoder.write_u16(0);
+
+        match self {
+            EdnsOption::Nsid(data) | EdnsOption::Unknown { data, .. } => encoder.write_bytes(data),
+            EdnsOption::ClientSubnet {
+                family,
+                source_prefix,
+                scope_prefix,
+                address,
+            } => {
+                encoder.write_u16(*family);
+                encoder.write_u8(*source_prefix);
+                encoder.write_u8(*scope_prefix);
+                encoder.write_bytes(address);
+            }
+            EdnsOption::Cookie { client, server } => {
+                encoder.write_bytes(client);
+                encoder.write_bytes(server);
+            }
+            EdnsOption::TcpKeepalive(timeout) => {
+                if let Some(timeout) = timeout {
+                    encoder.write_u16(*timeout);
+                }
+            }
+            EdnsOption::Padding(length) => encoder.write_bytes(&vec![0; *length as usize]),
+        }
+
+        let length = encoder.position() - length_pos - std::mem::size_of::<u16>();
+        encoder.set_u16_at(length_pos, length as u16);
+    }
+}
+
+impl Edns {
+    /// Reads an [`Edns`] from an OPT pseudo-record
+    pub fn from_record(record: &Record) -> Result<Self> {
+        if !record.name.is_root() {
+            return Err(Error::Owner(record.name.clone()));
+        }
+
+        let [extended_rcode, version, flags @ ..] = record.time_to_live.to_be_bytes();
+        let flags = u16::from_be_bytes(flags);
+
+        let data = match &record.rdata {
+            RData::Unknown(data) => &data[..],
+            _ => &[],
+        };
+
+        let mut bytes = Cursor::new(data);
+        let mut options = Vec::new();
+        while (bytes.position() as usize) < data.len() {
+            options.push(EdnsOption::from_bytes(&mut bytes)?);
+        }
+
+        Ok(Self {
+            udp_payload_size: u16::from(record.class),
+            extended_rcode,
+            version,
+            dnssec_ok: flags & Self::DO_MASK != 0,
+            options,
+        })
+    }
+
+    /// Converts an [`Edns`] to its OPT pseudo-record
+    pub fn to_record(&self) -> Record {
+        let flags = if self.dnssec_ok { Self::DO_MASK } else { 0 };
+        let [flags_higher, flags_lower] = flags.to_be_bytes();
+
+        let mut encoder = Encoder::uncompressed();
+        for option in &self.options {
+            option.encode(&mut encoder);
+        }
+
+        Record {
+            name: DomainName::root(),
+            qtype: QType::OPT,
+            class: QClass::from(self.udp_payload_size),
+            time_to_live: u32::from_be_bytes([
+                self.extended_rcode,
+                self.version,
+                flags_higher,
+                flags_lower,
+            ]),
+            rdata: RData::Unknown(encoder.into_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests decoding of the OPT record sent by `dig`, with a cookie option
+    #[test]
+    fn decode_edns() -> crate::record::Result<()> {
+        let opt_bytes =
+            b"\x00\x00\x29\x04\xd0\x00\x00\x80\x00\x00\x0c\x00\x0a\x00\x08\x01\x02\x03\x04\x05\x06\x07\x08";
+        let correct_edns = Edns {
+            udp_payload_size: 1232,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: true,
+            options: vec![EdnsOption::Cookie {
+                client: [1, 2, 3, 4, 5, 6, 7, 8],
+                server: vec![],
+            }],
+        };
+
+        let record = Record::from_bytes(&mut Cursor::new(&opt_bytes[..]))?;
+        let result_edns = Edns::from_record(&record).unwrap();
+
+        assert_eq!(result_edns, correct_edns);
+        assert_eq!(result_edns.to_record().into_bytes()?, opt_bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn edns_options_round_trip() -> Result<()> {
+        let edns = Edns {
+            udp_payload_size: 4096,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: false,
+            options: vec![
+                EdnsOption::Nsid(vec![]),
+                EdnsOption::ClientSubnet {
+                    family: 1,
+                    source_prefix: 24,
+                    scope_prefix: 0,
+                    address: vec![192, 0, 2],
+                },
+                EdnsOption::TcpKeepalive(Some(300)),
+                EdnsOption::Padding(4),
+                EdnsOption::Unknown {
+                    code: 65001,
+                    data: vec![0xde, 0xad],
+                },
+            ],
+        };
+
+        let result_edns = Edns::from_record(&edns.to_record())?;
+
+        assert_eq!(result_edns, edns);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_edns_invalid_option() {
+        // a cookie option must carry at least a full client cookie
+        let mut record = Edns::default().to_record();
+        record.rdata = RData::Unknown(b"\x00\x0a\x00\x02\x01\x02".to_vec());
+
+        let result = Edns::from_record(&record);
+
+        assert!(matches!(
+            result,
+            Err(Error::OptionLength {
+                code: EdnsOption::COOKIE,
+                length: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,52 @@
+//! Messages and records shared by the tests of the crate.
+
+use crate::{
+    dname::DomainName,
+    edns::Edns,
+    header::{Header, HeaderFlags},
+    message::Message,
+    qclass::QClass,
+    qtype::QType,
+    question::Question,
+    rdata::RData,
+    record::Record,
+};
+
+/// An IN record of the given name and data, with a TTL of an hour
+pub(crate) fn record(name: &str, rdata: RData) -> Record {
+    Record {
+        name: DomainName::new(name),
+        qtype: rdata.qtype().unwrap(),
+        class: QClass::IN,
+        time_to_live: 3600,
+        rdata,
+    }
+}
+
+/// A response for `www.example.com.` A holding a record in each section, along with EDNS(0)
+pub(crate) fn test_message() -> Message {
+    Message {
+        header: Header::new(0x1314, HeaderFlags::default().set_qr(true).finalize()),
+        questions: vec![Question {
+            qname: DomainName::new("www.example.com"),
+            qtype: QType::A,
+            qclass: QClass::IN,
+        }],
+        answers: vec![
+            record(
+                "www.example.com",
+                RData::CNAME(DomainName::new("example.com")),
+            ),
+            record("example.com", RData::A([93, 184, 216, 34].into())),
+        ],
+        authorities: vec![record(
+            "example.com",
+            RData::NS(DomainName::new("a.iana-servers.net")),
+        )],
+        additionals: vec![record(
+            "a.iana-servers.net",
+            RData::A([199, 43, 135, 53].into()),
+        )],
+        edns: Some(Edns::default()),
+    }
+}
@@ -2,7 +2,10 @@ use std::io::Cursor;
 
 use byteorder::{ByteOrder, NetworkEndian, ReadBytesExt};
 
-use crate::header::{Header, HeaderFlags, Result};
+use crate::{
+    encoder::Encoder,
+    header::{Header, HeaderFlags, Result},
+};
 
 impl HeaderFlags {
     fn as_u16(self) -> u16 {
@@ -71,6 +74,11 @@ impl Header {
         buf
     }
 
+    /// Writes a header to an [`Encoder`]
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_bytes(&self.into_bytes());
+    }
+
     /// Reads a header from a slice of bytes
     pub fn from_bytes(bytes: &mut Cursor<&[u8]>) -> Result<Self> {
         let mut buf = [0u16; 6];
@@ -78,8 +86,7 @@ impl Header {
         let [id, flags, num_questions, num_answers, num_authorities, num_additionals]: [u16; 6] =
             buf;
 
-        let flags = HeaderFlags::try_from(flags)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        let flags = HeaderFlags::try_from(flags).map_err(std::io::Error::other)?;
 
         Ok(Self {
             id,
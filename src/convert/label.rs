@@ -5,7 +5,7 @@ use crate::types::dname::label::*;
 impl Label {
     pub fn into_bytes(self) -> Vec<u8> {
         let size = self.0.len();
-        let mut buf = self.0.into_bytes();
+        let mut buf = self.0;
         buf.splice(0..0, [size as u8]);
         buf
     }
@@ -16,18 +16,29 @@ impl Label {
             dest_amt: dest.len(),
             source,
         })?;
-        let label = std::str::from_utf8(dest)
-            .map_err(|source| Error::Convert {
-                bytes: dest.to_vec(),
-                source,
-            })?
-            .to_string();
-        Ok(Self(label))
+        Ok(Self(dest.to_vec()))
     }
 }
 
+/// Writes label octets in their presentation form, as described in RFC 1035 section 5.1
+///
+/// Characters with a special meaning in master files are escaped as `\X`,
+/// and non-printable octets as `\DDD`, where DDD is the octet's decimal value.
+pub(crate) fn fmt_label(label: &[u8], f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for &byte in label {
+        match byte {
+            b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' => {
+                write!(f, "\\{}", byte as char)?
+            }
+            0x21..=0x7E => write!(f, "{}", byte as char)?,
+            _ => write!(f, "\\{byte:03}")?,
+        }
+    }
+    Ok(())
+}
+
 impl std::fmt::Display for Label {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        fmt_label(&self.0, f)
     }
 }
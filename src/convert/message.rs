@@ -1,8 +1,11 @@
 use std::io::Cursor;
 
 use crate::{
+    edns::{self, Edns},
+    encoder::Encoder,
     header::Header,
     message::{Message, Result},
+    qtype::QType,
     question::Question,
     record::Record,
 };
@@ -24,31 +27,197 @@ impl Message {
             .take(header.num_authorities as usize)
             .collect::<std::result::Result<Vec<Record>, crate::record::Error>>()?;
 
-        let additionals: Vec<Record> = std::iter::repeat_with(|| Record::from_bytes(bytes))
+        let mut additionals: Vec<Record> = std::iter::repeat_with(|| Record::from_bytes(bytes))
             .take(header.num_additionals as usize)
             .collect::<std::result::Result<Vec<Record>, crate::record::Error>>()?;
 
+        // the OPT pseudo-record is not a real additional record
+        let mut edns = None;
+        for opt in additionals.extract_if(.., |rec| rec.qtype == QType::OPT) {
+            if edns.replace(Edns::from_record(&opt)?).is_some() {
+                return Err(edns::Error::Duplicate.into());
+            }
+        }
+
         Ok(Self {
             header,
             questions,
             answers,
             authorities,
             additionals,
+            edns,
         })
     }
 
-    /// Converts a query [`Message`] to owned bytes
-    pub fn query_into_bytes(self) -> Vec<u8> {
+    /// Converts a [`Message`] to owned bytes, compressing domain names
+    ///
+    /// The header's section counts are taken from the lengths of the message's sections.
+    pub fn into_bytes(self) -> Result<Vec<u8>> {
+        let mut encoder = Encoder::new();
+        self.encode(&mut encoder)?;
+        Ok(encoder.into_bytes())
+    }
+
+    /// Writes a [`Message`] to an [`Encoder`]
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<()> {
+        let header = Header {
+            num_questions: self.questions.len() as u16,
+            num_answers: self.answers.len() as u16,
+            num_authorities: self.authorities.len() as u16,
+            num_additionals: (self.additionals.len() + self.edns.is_some() as usize) as u16,
+            ..self.header
+        };
+        header.encode(encoder);
+
+        for question in &self.questions {
+            question.encode(encoder);
+        }
+
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            record.encode(encoder)?;
+        }
+
+        if let Some(edns) = &self.edns {
+            edns.to_record().encode(encoder)?;
+        }
+        Ok(())
+    }
+
+    /// Converts a query [`Message`] to owned bytes, which may carry an OPT pseudo-record
+    pub fn query_into_bytes(self) -> Result<Vec<u8>> {
         assert_eq!(self.questions.len(), 1);
         assert_eq!(self.answers.len(), 0);
         assert_eq!(self.authorities.len(), 0);
         assert_eq!(self.additionals.len(), 0);
 
-        // header
-        let mut buf = self.header.into_bytes();
+        self.into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        convert::fixtures::{record, test_message},
+        dname::DomainName,
+        message::MsgSection,
+        qtype::QType,
+        rdata::RData,
+    };
+
+    #[test]
+    fn encode_message_counts() -> Result<()> {
+        let mut msg = Message::new_query(DomainName::new("example.com"), QType::A, false, false);
+        msg.answers
+            .push(record("example.com", RData::A([93, 184, 216, 34].into())));
+        msg.additionals
+            .push(record("example.com", RData::A([93, 184, 216, 35].into())));
+
+        let bytes = msg.into_bytes()?;
+        let header = Header::from_bytes(&mut Cursor::new(&bytes[..]))?;
+
+        assert_eq!(header.num_questions, 1);
+        assert_eq!(header.num_answers, 1);
+        assert_eq!(header.num_authorities, 0);
+        assert_eq!(header.num_additionals, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn encode_message_compressed() -> Result<()> {
+        let mut msg = Message::new_query(DomainName::new("example.com"), QType::NS, false, false);
+        for ns in ["a.iana-servers.net", "b.iana-servers.net"] {
+            msg.answers
+                .push(record("example.com", RData::NS(DomainName::new(ns))));
+        }
+
+        let uncompressed_len = {
+            let mut encoder = Encoder::uncompressed();
+            msg.encode(&mut encoder)?;
+            encoder.position()
+        };
+        let bytes = msg.clone().into_bytes()?;
+
+        // both answer owners point back to the question name,
+        // and the second nameserver reuses "iana-servers.net" from the first
+        assert_eq!(&bytes[29..31], b"\xc0\x0c");
+        assert!(bytes.len() < uncompressed_len);
+
+        let result_msg = Message::from_bytes(&mut Cursor::new(&bytes[..]))?;
+        assert_eq!(result_msg.answers, msg.answers);
+        Ok(())
+    }
+
+    #[test]
+    fn message_round_trip() -> Result<()> {
+        let msg = test_message();
+
+        let bytes = msg.clone().into_bytes()?;
+        let result_msg = Message::from_bytes(&mut Cursor::new(&bytes[..]))?;
+
+        assert_eq!(result_msg.questions, msg.questions);
+        assert_eq!(result_msg.answers, msg.answers);
+        assert_eq!(result_msg.authorities, msg.authorities);
+        assert_eq!(result_msg.additionals, msg.additionals);
+        assert_eq!(result_msg.edns, msg.edns);
+        assert_eq!(result_msg.header.num_answers, 2);
+        assert_eq!(result_msg.header.num_additionals, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn message_rrsets() {
+        let mut msg =
+            Message::new_query(DomainName::new("www.example.com"), QType::A, false, false);
+        msg.answers = vec![
+            record(
+                "www.example.com",
+                RData::CNAME(DomainName::new("example.com")),
+            ),
+            record("example.com", RData::A([93, 184, 216, 34].into())),
+            record("EXAMPLE.com", RData::A([93, 184, 216, 35].into())),
+            record("other.example.com", RData::A([93, 184, 216, 36].into())),
+        ];
+
+        assert_eq!(
+            msg.get_records_by_type_from(QType::A, MsgSection::Answers)
+                .count(),
+            3
+        );
+
+        let name = DomainName::new("example.com");
+        let rrset: Vec<&Record> = msg
+            .get_rrset_from(&name, QType::A, MsgSection::Answers)
+            .collect();
+        assert_eq!(rrset, [&msg.answers[1], &msg.answers[2]]);
+        assert_eq!(
+            msg.get_rrset_from(&name, QType::CNAME, MsgSection::Answers)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn extended_response_code() -> Result<()> {
+        let mut msg = Message::new_query(DomainName::new("example.com"), QType::A, false, false);
+        msg.header.flags.set_response_code(9)?;
+        assert_eq!(msg.response_code(), 9);
 
-        // questions
-        buf.extend(self.questions.into_iter().flat_map(|q| q.into_bytes()));
-        buf
+        // BADVERS, whose lower bits in the header read as NOERROR
+        msg.header.flags.set_response_code(0)?;
+        msg.edns = Some(Edns {
+            extended_rcode: 1,
+            ..Edns::default()
+        });
+        let bytes = msg.into_bytes()?;
+        let result_msg = Message::from_bytes(&mut Cursor::new(&bytes[..]))?;
+        assert_eq!(result_msg.response_code(), 16);
+        Ok(())
     }
 }
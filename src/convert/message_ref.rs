@@ -0,0 +1,319 @@
+use std::io::Cursor;
+
+use super::{dname::WireLabels, label::fmt_label};
+use crate::{
+    dname::{self, DomainName, Label},
+    header::Header,
+    message::{self, Message, MsgSection},
+    message_ref::{MessageRef, NameRef, QuestionRef, RecordRef},
+    qclass::QClass,
+    qtype::QType,
+    question::{self, Question},
+    rdata::{self, RData},
+    record::{self, Record},
+};
+
+fn read_bytes<const N: usize>(msg: &[u8], pos: usize) -> std::io::Result<[u8; N]> {
+    msg.get(pos..pos + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into())
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> std::io::Result<u16> {
+    read_bytes(msg, pos).map(u16::from_be_bytes)
+}
+
+fn read_u32(msg: &[u8], pos: usize) -> std::io::Result<u32> {
+    read_bytes(msg, pos).map(u32::from_be_bytes)
+}
+
+impl<'a> NameRef<'a> {
+    /// Checks the name found at `pos`, returning it along with the position right after it
+    fn read(msg: &'a [u8], pos: usize) -> dname::Result<(Self, usize)> {
+        let mut wire_labels = WireLabels::new(msg, pos);
+        for label in wire_labels.by_ref() {
+            label?;
+        }
+        // a name without errors always ends with a terminator or pointer
+        let end = wire_labels.end().unwrap_or(msg.len());
+
+        Ok((Self { msg, offset: pos }, end))
+    }
+
+    /// Iterates over the octets of each label, from the leftmost (least significant) one
+    pub fn labels(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        // the name was checked while parsing the message, so walking it does not fail
+        WireLabels::new(self.msg, self.offset).map_while(std::result::Result::ok)
+    }
+
+    /// Whether this is the root
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    /// Copies the name's labels into an owned [`DomainName`]
+    pub fn to_domain_name(&self) -> DomainName {
+        DomainName {
+            labels: self
+                .labels()
+                .map(|label| Label::new(label.to_vec()))
+                .collect(),
+            absolute: true,
+        }
+    }
+}
+
+impl PartialEq<DomainName> for NameRef<'_> {
+    /// Compares the names without regard to the case of ASCII letters, without copying any labels
+    fn eq(&self, other: &DomainName) -> bool {
+        let mut labels = self.labels();
+        other.labels().all(|other_label| {
+            labels
+                .next()
+                .is_some_and(|label| label.eq_ignore_ascii_case(other_label.as_bytes()))
+        }) && labels.next().is_none()
+    }
+}
+
+impl std::fmt::Display for NameRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_root() {
+            return f.write_str(".");
+        }
+
+        for label in self.labels() {
+            fmt_label(label, f)?;
+            f.write_str(".")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for NameRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NameRef").field(&self.to_string()).finish()
+    }
+}
+
+impl<'a> QuestionRef<'a> {
+    /// Checks the question found at `pos`, returning it along with the position right after it
+    fn read(msg: &'a [u8], pos: usize) -> question::Result<(Self, usize)> {
+        let (qname, pos) = NameRef::read(msg, pos)?;
+        let qtype = QType::from(read_u16(msg, pos)?);
+        let qclass = QClass::from(read_u16(msg, pos + 2)?);
+
+        Ok((
+            Self {
+                qname,
+                qtype,
+                qclass,
+            },
+            pos + 4,
+        ))
+    }
+
+    /// Copies the question into an owned [`Question`]
+    pub fn to_question(&self) -> Question {
+        Question {
+            qname: self.qname.to_domain_name(),
+            qtype: self.qtype,
+            qclass: self.qclass,
+        }
+    }
+}
+
+impl<'a> RecordRef<'a> {
+    /// Checks the record found at `pos`, returning it along with the position right after it
+    fn read(msg: &'a [u8], pos: usize) -> record::Result<(Self, usize)> {
+        let (name, pos) = NameRef::read(msg, pos)?;
+        let qtype = QType::from(read_u16(msg, pos)?);
+        let class = QClass::from(read_u16(msg, pos + 2)?);
+        let time_to_live = read_u32(msg, pos + 4)?;
+        let data_length = read_u16(msg, pos + 8)? as usize;
+
+        let rdata_offset = pos + 10;
+        let rdata = msg
+            .get(rdata_offset..rdata_offset + data_length)
+            .ok_or(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+
+        Ok((
+            Self {
+                name,
+                qtype,
+                class,
+                time_to_live,
+                rdata_offset,
+                rdata,
+            },
+            rdata_offset + data_length,
+        ))
+    }
+
+    /// Decodes the record's data
+    pub fn data(&self) -> rdata::Result<RData> {
+        let mut bytes = Cursor::new(self.name.msg);
+        bytes.set_position(self.rdata_offset as u64);
+        RData::from_bytes(&mut bytes, self.qtype, self.rdata.len() as u16)
+    }
+
+    /// Copies the record into an owned [`Record`], decoding its data
+    pub fn to_record(&self) -> record::Result<Record> {
+        Ok(Record {
+            name: self.name.to_domain_name(),
+            qtype: self.qtype,
+            class: self.class,
+            time_to_live: self.time_to_live,
+            rdata: self.data()?,
+        })
+    }
+}
+
+impl<'a> MessageRef<'a> {
+    /// Checks the structure of the message held in `msg`, without copying any of it
+    ///
+    /// Every question and record, including their names, is walked once here,
+    /// so that iterating over them afterwards cannot fail and the iterators need not return results.
+    /// Nothing is kept from this pass but the offsets of the sections:
+    /// the iterators walk the message again, lazily.
+    /// Record data is only checked when decoded.
+    pub fn parse(msg: &'a [u8]) -> message::Result<Self> {
+        let header = Header::from_bytes(&mut Cursor::new(msg))?;
+        let mut pos = 6 * std::mem::size_of::<u16>();
+
+        let questions_start = pos;
+        for _ in 0..header.num_questions {
+            (_, pos) = QuestionRef::read(msg, pos)?;
+        }
+
+        let mut sections = [questions_start, 0, 0, 0];
+        for (idx, num_records) in [
+            header.num_answers,
+            header.num_authorities,
+            header.num_additionals,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sections[idx + 1] = pos;
+            for _ in 0..num_records {
+                (_, pos) = RecordRef::read(msg, pos)?;
+            }
+        }
+
+        Ok(Self {
+            msg,
+            header,
+            sections,
+        })
+    }
+
+    /// Iterates over the question section
+    pub fn questions(&self) -> impl Iterator<Item = QuestionRef<'a>> + 'a {
+        let msg = self.msg;
+        let mut pos = self.sections[0];
+        (0..self.header.num_questions).map_while(move |_| {
+            let (question, end) = QuestionRef::read(msg, pos).ok()?;
+            pos = end;
+            Some(question)
+        })
+    }
+
+    /// Iterates over the records of the given section
+    ///
+    /// Unlike [`Message::additionals`], the additional section includes the OPT pseudo-record, if any.
+    pub fn records(&self, section: MsgSection) -> impl Iterator<Item = RecordRef<'a>> + 'a {
+        let (mut pos, num_records) = match section {
+            MsgSection::Answers => (self.sections[1], self.header.num_answers),
+            MsgSection::Authorities => (self.sections[2], self.header.num_authorities),
+            MsgSection::Additionals => (self.sections[3], self.header.num_additionals),
+        };
+
+        let msg = self.msg;
+        (0..num_records).map_while(move |_| {
+            let (record, end) = RecordRef::read(msg, pos).ok()?;
+            pos = end;
+            Some(record)
+        })
+    }
+
+    pub fn answers(&self) -> impl Iterator<Item = RecordRef<'a>> + 'a {
+        self.records(MsgSection::Answers)
+    }
+
+    pub fn authorities(&self) -> impl Iterator<Item = RecordRef<'a>> + 'a {
+        self.records(MsgSection::Authorities)
+    }
+
+    pub fn additionals(&self) -> impl Iterator<Item = RecordRef<'a>> + 'a {
+        self.records(MsgSection::Additionals)
+    }
+
+    /// Decodes the whole message into an owned [`Message`]
+    pub fn to_message(&self) -> message::Result<Message> {
+        Message::from_bytes(&mut Cursor::new(self.msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::convert::fixtures::test_message;
+
+    #[test]
+    fn message_ref_matches_owned() -> message::Result<()> {
+        let msg = test_message();
+        let bytes = msg.clone().into_bytes()?;
+
+        let msg_ref = MessageRef::parse(&bytes)?;
+
+        let questions: Vec<Question> = msg_ref.questions().map(|q| q.to_question()).collect();
+        assert_eq!(questions, msg.questions);
+
+        let answers = msg_ref
+            .answers()
+            .map(|rec| rec.to_record())
+            .collect::<record::Result<Vec<Record>>>()?;
+        assert_eq!(answers, msg.answers);
+        assert_eq!(msg_ref.authorities().count(), 1);
+        // the OPT pseudo-record is kept in the borrowed additional section
+        assert_eq!(msg_ref.additionals().count(), 2);
+
+        let owned_msg = msg_ref.to_message()?;
+        assert_eq!(owned_msg.authorities, msg.authorities);
+        assert_eq!(owned_msg.additionals, msg.additionals);
+        assert_eq!(owned_msg.edns, msg.edns);
+        Ok(())
+    }
+
+    #[test]
+    fn name_ref_follows_pointers() -> message::Result<()> {
+        let bytes = test_message().into_bytes()?;
+        let msg_ref = MessageRef::parse(&bytes)?;
+
+        let ns = msg_ref.authorities().next().unwrap();
+        let Ok(RData::NS(ns_name)) = ns.data() else {
+            panic!("expected NS data");
+        };
+
+        assert_eq!(ns.name, DomainName::new("EXAMPLE.com"));
+        assert_ne!(ns.name, DomainName::new("com"));
+        assert_eq!(ns.name.to_string(), "example.com.");
+        assert_eq!(ns_name, DomainName::new("a.iana-servers.net"));
+        assert_eq!(ns.rdata().len(), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn message_ref_truncated() {
+        let bytes = test_message().into_bytes().unwrap();
+
+        // every strict prefix of the message is missing part of a section
+        for len in 0..bytes.len() {
+            assert!(
+                MessageRef::parse(&bytes[..len]).is_err(),
+                "accepted {len} bytes"
+            );
+        }
+    }
+}
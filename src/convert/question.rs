@@ -1,9 +1,10 @@
 use std::io::Cursor;
 
-use byteorder::{NetworkEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{NetworkEndian, ReadBytesExt};
 
 use crate::{
     dname::DomainName,
+    encoder::Encoder,
     qclass::QClass,
     qtype::QType,
     question::{Question, Result},
@@ -12,19 +13,23 @@ use crate::{
 impl Question {
     /// Converts a [`Question`] to owned bytes
     pub fn into_bytes(self) -> Vec<u8> {
-        let mut buf = self.qname.into_bytes();
-
-        buf.write_u16::<NetworkEndian>(self.qtype.into()).unwrap();
-        buf.write_u16::<NetworkEndian>(self.qclass.into()).unwrap();
+        let mut encoder = Encoder::uncompressed();
+        self.encode(&mut encoder);
+        encoder.into_bytes()
+    }
 
-        buf
+    /// Writes a [`Question`] to an [`Encoder`]
+    pub fn encode(&self, encoder: &mut Encoder) {
+        self.qname.encode(encoder);
+        encoder.write_u16(self.qtype.into());
+        encoder.write_u16(self.qclass.into());
     }
 
     /// Reads a [`Question`] from a slice of bytes
     pub fn from_bytes(bytes: &mut Cursor<&[u8]>) -> Result<Self> {
         let qname = DomainName::from_bytes(bytes)?;
-        let qtype = QType::try_from(bytes.read_u16::<NetworkEndian>()?)?;
-        let qclass = QClass::try_from(bytes.read_u16::<NetworkEndian>()?)?;
+        let qtype = QType::from(bytes.read_u16::<NetworkEndian>()?);
+        let qclass = QClass::from(bytes.read_u16::<NetworkEndian>()?);
 
         Ok(Self {
             qname,
@@ -66,4 +71,21 @@ mod tests {
         assert_eq!(result_question, correct_question);
         Ok(())
     }
+
+    #[test]
+    fn decode_unknown_question() -> Result<()> {
+        let correct_question = Question {
+            qname: DomainName::new("google.com"),
+            qclass: QClass::Unknown(99),
+            qtype: QType::Unknown(46),
+        };
+
+        let mut bytes = Cursor::new(&b"\x06google\x03com\x00\x00\x2e\x00\x63"[..]);
+        let result_question = Question::from_bytes(&mut bytes)?;
+
+        assert_eq!(result_question, correct_question);
+        assert_eq!(QType::from(46), QType::Unknown(46));
+        assert_eq!(QType::from(1).to_string(), "A");
+        Ok(())
+    }
 }
@@ -0,0 +1,261 @@
+use std::io::{Cursor, Read};
+
+use byteorder::{NetworkEndian, ReadBytesExt};
+
+use crate::{
+    dname::DomainName,
+    encoder::Encoder,
+    qtype::QType,
+    rdata::{Error, RData, Result, Soa},
+};
+
+/// Reads a length-prefixed character string
+fn read_char_string(bytes: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+    let size = bytes.read_u8()?;
+    let mut string = vec![0; size as usize];
+    bytes.read_exact(&mut string)?;
+    Ok(string)
+}
+
+/// The most octets a character string can hold, as its length is a single octet
+const MAX_CHAR_STRING: usize = u8::MAX as usize;
+
+/// Writes a length-prefixed character string, which must fit within the octets its length can count
+fn encode_char_string(string: &[u8], encoder: &mut Encoder) -> Result<()> {
+    let size =
+        u8::try_from(string.len()).map_err(|_| Error::CharStringTooLong { size: string.len() })?;
+    encoder.write_u8(size);
+    encoder.write_bytes(string);
+    Ok(())
+}
+
+impl RData {
+    /// Reads [`RData`] of the given type from a slice of bytes
+    ///
+    /// The cursor must be over the whole message, since domain names within the data may be compressed.
+    pub fn from_bytes(bytes: &mut Cursor<&[u8]>, qtype: QType, length: u16) -> Result<Self> {
+        let start = bytes.position();
+        let end = start + length as u64;
+
+        let data = match qtype {
+            QType::A => {
+                let mut octets = [0u8; 4];
+                bytes.read_exact(&mut octets)?;
+                RData::A(octets.into())
+            }
+            QType::AAAA => {
+                let mut octets = [0u8; 16];
+                bytes.read_exact(&mut octets)?;
+                RData::AAAA(octets.into())
+            }
+            QType::NS => RData::NS(DomainName::from_bytes(bytes)?),
+            QType::CNAME => RData::CNAME(DomainName::from_bytes(bytes)?),
+            QType::PTR => RData::PTR(DomainName::from_bytes(bytes)?),
+            QType::SOA => RData::SOA(Soa {
+                mname: DomainName::from_bytes(bytes)?,
+                rname: DomainName::from_bytes(bytes)?,
+                serial: bytes.read_u32::<NetworkEndian>()?,
+                refresh: bytes.read_u32::<NetworkEndian>()?,
+                retry: bytes.read_u32::<NetworkEndian>()?,
+                expire: bytes.read_u32::<NetworkEndian>()?,
+                minimum: bytes.read_u32::<NetworkEndian>()?,
+            }),
+            QType::MX => RData::MX {
+                preference: bytes.read_u16::<NetworkEndian>()?,
+                exchange: DomainName::from_bytes(bytes)?,
+            },
+            QType::TXT => {
+                let mut strings = Vec::new();
+                while bytes.position() < end {
+                    strings.push(read_char_string(bytes)?);
+                }
+                RData::TXT(strings)
+            }
+            QType::HINFO => RData::HINFO {
+                cpu: read_char_string(bytes)?,
+                os: read_char_string(bytes)?,
+            },
+            _ => {
+                let mut data = vec![0; length as usize];
+                bytes.read_exact(&mut data)?;
+                if qtype == QType::NULL {
+                    RData::NULL(data)
+                } else {
+                    RData::Unknown(data)
+                }
+            }
+        };
+
+        let actual = bytes.position() - start;
+        if actual != length as u64 {
+            return Err(Error::Length {
+                qtype,
+                expected: length,
+                actual,
+            });
+        }
+
+        Ok(data)
+    }
+
+    /// Writes [`RData`] to an [`Encoder`]
+    ///
+    /// Only the domain names of the types defined in RFC 1035 are compressed, per RFC 3597.
+    /// Fails if a character string cannot be written, as HINFO strings longer than 255 octets.
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<()> {
+        match self {
+            RData::A(addr) => encoder.write_bytes(&addr.octets()),
+            RData::AAAA(addr) => encoder.write_bytes(&addr.octets()),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => name.encode(encoder),
+            RData::SOA(soa) => {
+                soa.mname.encode(encoder);
+                soa.rname.encode(encoder);
+                encoder.write_u32(soa.serial);
+                encoder.write_u32(soa.refresh);
+                encoder.write_u32(soa.retry);
+                encoder.write_u32(soa.expire);
+                encoder.write_u32(soa.minimum);
+            }
+            RData::MX {
+                preference,
+                exchange,
+            } => {
+                encoder.write_u16(*preference);
+                exchange.encode(encoder);
+            }
+            RData::TXT(strings) => {
+                // longer strings are split, as their consumers join them back (RFC 7208 section 3.3)
+                for string in strings {
+                    if string.is_empty() {
+                        encode_char_string(string, encoder)?;
+                    }
+                    for chunk in string.chunks(MAX_CHAR_STRING) {
+                        encode_char_string(chunk, encoder)?;
+                    }
+                }
+            }
+            RData::HINFO { cpu, os } => {
+                encode_char_string(cpu, encoder)?;
+                encode_char_string(os, encoder)?;
+            }
+            RData::NULL(data) | RData::Unknown(data) => encoder.write_bytes(data),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(qtype: QType, data: RData) -> Result<()> {
+        let mut encoder = Encoder::new();
+        data.encode(&mut encoder)?;
+        let bytes = encoder.into_bytes();
+
+        let result_data =
+            RData::from_bytes(&mut Cursor::new(&bytes[..]), qtype, bytes.len() as u16)?;
+
+        assert_eq!(result_data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn rdata_round_trip() -> Result<()> {
+        round_trip(QType::A, RData::A([93, 184, 216, 34].into()))?;
+        round_trip(
+            QType::AAAA,
+            RData::AAAA("2606:2800:220:1::1".parse().unwrap()),
+        )?;
+        round_trip(QType::NS, RData::NS(DomainName::new("a.iana-servers.net")))?;
+        round_trip(
+            QType::SOA,
+            RData::SOA(Soa {
+                mname: DomainName::new("ns.icann.org"),
+                rname: DomainName::new("noc.dns.icann.org"),
+                serial: 2024081412,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 3600,
+            }),
+        )?;
+        round_trip(
+            QType::MX,
+            RData::MX {
+                preference: 10,
+                exchange: DomainName::new("mail.example.com"),
+            },
+        )?;
+        round_trip(
+            QType::TXT,
+            RData::TXT(vec![b"v=spf1 -all".to_vec(), vec![]]),
+        )?;
+        round_trip(
+            QType::HINFO,
+            RData::HINFO {
+                cpu: b"RFC8482".to_vec(),
+                os: vec![],
+            },
+        )?;
+        round_trip(QType::NULL, RData::NULL(vec![0, 1, 2]))
+    }
+
+    #[test]
+    fn encode_long_txt() -> Result<()> {
+        let value = vec![b'a'; 300];
+        let mut encoder = Encoder::new();
+        RData::TXT(vec![value.clone()]).encode(&mut encoder)?;
+        let bytes = encoder.into_bytes();
+
+        assert_eq!(bytes.len(), 302);
+        let result_data =
+            RData::from_bytes(&mut Cursor::new(&bytes[..]), QType::TXT, bytes.len() as u16)?;
+        let RData::TXT(strings) = result_data else {
+            panic!("decoded {result_data:?}");
+        };
+        assert_eq!(strings.iter().map(Vec::len).collect::<Vec<_>>(), [255, 45]);
+        assert_eq!(strings.concat(), value);
+
+        // HINFO strings cannot be split
+        let hinfo = RData::HINFO {
+            cpu: value,
+            os: vec![],
+        };
+        assert!(matches!(
+            hinfo.encode(&mut Encoder::new()),
+            Err(Error::CharStringTooLong { size: 300 })
+        ));
+        Ok(())
+    }
+
+    /// Tests decoding of an MX exchange that points back into the message
+    #[test]
+    fn decode_compressed_rdata() -> Result<()> {
+        let bytes = b"\x07example\x03com\x00\x00\x0a\x04mail\xc0\x00";
+        let mut cursor = Cursor::new(&bytes[..]);
+        cursor.set_position(13);
+
+        let result_data = RData::from_bytes(&mut cursor, QType::MX, 9)?;
+
+        assert_eq!(
+            result_data,
+            RData::MX {
+                preference: 10,
+                exchange: DomainName::new("mail.example.com"),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decode_rdata_length_mismatch() {
+        let bytes = b"\x01\x02\x03\x04\x05";
+
+        let result = RData::from_bytes(&mut Cursor::new(&bytes[..]), QType::A, 5);
+        assert!(matches!(result, Err(Error::Length { .. })));
+
+        let result = RData::from_bytes(&mut Cursor::new(&bytes[..]), QType::MX, 5);
+        assert!(result.is_err());
+    }
+}
@@ -1,37 +1,54 @@
-use std::io::{Cursor, Read};
+use std::io::Cursor;
 
 use byteorder::{NetworkEndian, ReadBytesExt};
 
 use crate::{
     dname::DomainName,
+    encoder::Encoder,
     qclass::QClass,
     qtype::QType,
-    record::{Record, Result},
+    rdata::RData,
+    record::{Error, Record, Result},
 };
 
 impl Record {
+    /// Converts a [`Record`] to owned bytes, without name compression
+    pub fn into_bytes(self) -> Result<Vec<u8>> {
+        let mut encoder = Encoder::uncompressed();
+        self.encode(&mut encoder)?;
+        Ok(encoder.into_bytes())
+    }
+
+    /// Writes a [`Record`] to an [`Encoder`]
+    pub fn encode(&self, encoder: &mut Encoder) -> Result<()> {
+        self.name.encode(encoder);
+        encoder.write_u16(self.qtype.into());
+        encoder.write_u16(self.class.into());
+        encoder.write_u32(self.time_to_live);
+
+        // the data length is only known once the (possibly compressed) data is written
+        let length_pos = encoder.position();
+        encoder.write_u16(0);
+
+        self.rdata.encode(encoder)?;
+
+        let data_length = encoder.position() - length_pos - std::mem::size_of::<u16>();
+        let data_length =
+            u16::try_from(data_length).map_err(|_| Error::DataTooLong { size: data_length })?;
+        encoder.set_u16_at(length_pos, data_length);
+        Ok(())
+    }
+
     /// Reads a [`Record`] from a slice of bytes
     pub fn from_bytes(bytes: &mut Cursor<&[u8]>) -> Result<Self> {
         let qname = DomainName::from_bytes(bytes)?;
-        let qtype = QType::try_from(bytes.read_u16::<NetworkEndian>()?)?;
-        let qclass = QClass::try_from(bytes.read_u16::<NetworkEndian>()?)?;
+        let qtype = QType::from(bytes.read_u16::<NetworkEndian>()?);
+        let qclass = QClass::from(bytes.read_u16::<NetworkEndian>()?);
         let ttl = bytes.read_u32::<NetworkEndian>()?;
 
         let data_length = bytes.read_u16::<NetworkEndian>()?;
 
-        let data = match qtype {
-            QType::NS | QType::CNAME => String::from(DomainName::from_bytes(bytes)?).into_bytes(),
-            QType::A => {
-                let mut data = vec![0; data_length as usize];
-                bytes.read_exact(&mut data)?;
-                data[..4].to_vec()
-            }
-            _ => {
-                let mut data = vec![0; data_length as usize];
-                bytes.read_exact(&mut data)?;
-                data
-            }
-        };
+        let data = RData::from_bytes(bytes, qtype, data_length)?;
 
         Ok(Self {
             name: qname,
@@ -47,6 +64,36 @@ impl Record {
 mod tests {
     use super::*;
 
+    #[test]
+    fn encode_record() -> Result<()> {
+        let record = Record {
+            name: DomainName::new("www.example.com"),
+            qtype: QType::A,
+            class: QClass::IN,
+            time_to_live: 21147,
+            rdata: RData::A([93, 184, 216, 34].into()),
+        };
+
+        let correct_bytes =
+            b"\x03www\x07example\x03com\x00\x00\x01\x00\x01\x00\x00R\x9b\x00\x04]\xb8\xd8\"";
+        let result_bytes = record.into_bytes()?;
+
+        assert_eq!(result_bytes, correct_bytes);
+
+        let oversized = Record {
+            name: DomainName::new("www.example.com"),
+            qtype: QType::NULL,
+            class: QClass::IN,
+            time_to_live: 21147,
+            rdata: RData::NULL(vec![0; 65536]),
+        };
+        assert!(matches!(
+            oversized.into_bytes(),
+            Err(Error::DataTooLong { size: 65536 })
+        ));
+        Ok(())
+    }
+
     #[test]
     fn decode_record() -> Result<()> {
         let record_bytes = b"`V\x81\x80\x00\x01\x00\x01\x00\x00\x00\x00\x03www\x07example\x03com\x00\x00\x01\x00\x01\xc0\x0c\x00\x01\x00\x01\x00\x00R\x9b\x00\x04]\xb8\xd8\"";
@@ -55,7 +102,7 @@ mod tests {
             qtype: QType::A,
             class: QClass::IN,
             time_to_live: 21147,
-            rdata: b"]\xb8\xd8\"".to_vec(),
+            rdata: RData::A([93, 184, 216, 34].into()),
         };
 
         let mut rec_bytes_reader = Cursor::new(&record_bytes[..]);
@@ -69,4 +116,26 @@ mod tests {
         assert_eq!(result_record, correct_record);
         Ok(())
     }
+
+    /// Tests that record types and classes without a dedicated variant survive a round trip
+    #[test]
+    fn unknown_record_round_trip() -> Result<()> {
+        let record_bytes =
+            b"\x07example\x03com\x00\x00\x41\x00\x63\x00\x00\x0e\x10\x00\x03\x00\x01\x00";
+        let correct_record = Record {
+            name: DomainName::new("example.com"),
+            qtype: QType::Unknown(65),
+            class: QClass::Unknown(99),
+            time_to_live: 3600,
+            rdata: RData::Unknown(vec![0, 1, 0]),
+        };
+
+        let result_record = Record::from_bytes(&mut Cursor::new(&record_bytes[..]))?;
+
+        assert_eq!(result_record, correct_record);
+        assert_eq!(result_record.qtype.to_string(), "TYPE65");
+        assert_eq!(result_record.class.to_string(), "CLASS99");
+        assert_eq!(result_record.into_bytes()?, record_bytes);
+        Ok(())
+    }
 }
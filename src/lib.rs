@@ -1,4 +1,5 @@
 mod convert;
+pub mod resolver;
 mod types;
 
 pub use types::*;
@@ -2,26 +2,117 @@ use clap::Parser;
 use tracing_subscriber::prelude::*;
 
 use std::{
-    io::Cursor,
-    net::{SocketAddr, UdpSocket},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
 };
 
 use dirt::{
-    message::{Message, MsgSection, Result as MsgResult},
+    dname::DomainName,
+    qclass::QClass,
     qtype::QType,
+    resolver::{
+        hints, Cache, CacheKey, Error as ResolverError, IpPreference, Lookup, ResolvConf, Resolver,
+        ResolverConfig, Result as ResolverResult, RootHints,
+    },
 };
 
 #[derive(Parser)]
 #[command(author, version, about)]
+#[command(after_help = "Exit status:
+  0  the name was resolved
+  1  the nameservers could not be reached, sent invalid responses, or led to a loop
+  3  the domain name does not exist (NXDOMAIN)
+  4  the domain name has no records of the requested type (NODATA)
+  5  the nameservers failed to process the query (SERVFAIL)
+  6  the nameservers refused the query (REFUSED)
+  7  the nameservers responded with another error")]
 struct Arguments {
-    /// Requested domain name
-    request: String,
+    /// Requested domain name, qualified with the resolv.conf search list unless it ends with a dot
+    request: DomainName,
     /// Requested IP V6 domains
     #[arg(short = '6', long = "ipv6", default_value_t)]
     ip_v6: bool,
+    /// Seconds to wait for a response to the first attempt of a query [default: 5, or the resolv.conf timeout with --stub]
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+    /// Number of times a query is retried across the candidate nameservers [default: 2, or one less than the resolv.conf attempts with --stub]
+    #[arg(long)]
+    retries: Option<u32>,
+    /// Seconds a query may wait for responses in total, across its retries and nameservers
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = ResolverConfig::default().deadline.as_secs())]
+    deadline: u64,
+    /// Number of queries a single resolution may send
+    #[arg(long, default_value_t = ResolverConfig::default().max_queries)]
+    max_queries: u32,
+    /// Address family used to reach nameservers
+    #[arg(long, value_enum, default_value_t = Transport::Ipv4)]
+    transport: Transport,
+    /// File listing the root nameservers, in the format of IANA's named.root [default: built-in copy]
+    #[arg(long, value_name = "FILE", value_parser = load_root_hints)]
+    root_hints: Option<RootHints>,
+    /// Send recursive queries to the nameservers of resolv.conf, instead of iterating from the root nameservers
+    #[arg(long, default_value_t)]
+    stub: bool,
+    /// The resolv.conf file whose search list qualifies relative names, and whose nameservers are used with --stub
+    #[arg(long, value_name = "FILE", default_value = ResolvConf::DEFAULT_PATH)]
+    resolv_conf: PathBuf,
+    /// Resolve the requested name as is, without trying the resolv.conf search list
+    #[arg(long, default_value_t)]
+    no_search: bool,
+    /// Neither load nor save the cache kept between runs
+    #[arg(long, default_value_t)]
+    no_cache: bool,
 }
 
-fn main() {
+/// Mirrors [`IpPreference`], to be parsed from the command line
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Transport {
+    /// Prefer IPv4, falling back to IPv6
+    Ipv4,
+    /// Prefer IPv6, falling back to IPv4
+    Ipv6,
+    /// Only use IPv4
+    Ipv4Only,
+    /// Only use IPv6
+    Ipv6Only,
+}
+
+impl From<Transport> for IpPreference {
+    fn from(transport: Transport) -> Self {
+        match transport {
+            Transport::Ipv4 => Self::Ipv4,
+            Transport::Ipv6 => Self::Ipv6,
+            Transport::Ipv4Only => Self::Ipv4Only,
+            Transport::Ipv6Only => Self::Ipv6Only,
+        }
+    }
+}
+
+impl Arguments {
+    /// The resolver's configuration, where the options given override those of resolv.conf
+    fn config(&self, resolv_conf: Option<&ResolvConf>) -> ResolverConfig {
+        let mut config = ResolverConfig {
+            ip_preference: self.transport.into(),
+            max_queries: self.max_queries,
+            deadline: Duration::from_secs(self.deadline),
+            ..Default::default()
+        };
+        if let Some(resolv_conf) = resolv_conf {
+            config = resolv_conf.resolver_config(config);
+        }
+        if let Some(timeout) = self.timeout {
+            config.timeout = Duration::from_secs(timeout);
+        }
+        if let Some(retries) = self.retries {
+            config.retries = retries;
+        }
+        config
+    }
+}
+
+fn main() -> ExitCode {
     let fmt_layer = tracing_subscriber::fmt::layer().with_target(false);
 
     let filter_layer = tracing_subscriber::EnvFilter::from_default_env();
@@ -34,107 +125,131 @@ fn main() {
     let args = Arguments::parse();
 
     match lookup_domain(&args) {
-        Ok(ip) => println!("{ip}"),
-        Err(e) => eprintln!("{e}"),
+        Ok(lookup) => {
+            if lookup.question.qname != args.request {
+                eprintln!(
+                    "\"{}\" was found as \"{}\"",
+                    args.request, lookup.question.qname
+                );
+            }
+            for record in &lookup.records {
+                println!("{}", record.rdata);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            exit_code(&e)
+        }
     }
 }
 
-/// Returns a ready-to-use UDP socket connected to the given address
-#[tracing::instrument]
-fn setup_udp_socket_to(dns_server_addr: SocketAddr) -> std::io::Result<UdpSocket> {
-    let udp_sock = match dns_server_addr {
-        SocketAddr::V4(_) => UdpSocket::bind((std::net::Ipv4Addr::UNSPECIFIED, 0))?,
-        SocketAddr::V6(_) => UdpSocket::bind((std::net::Ipv6Addr::UNSPECIFIED, 0))?,
-    };
-
-    tracing::trace!(
-        "Successfully bound to local addr: {}",
-        udp_sock.local_addr().unwrap()
-    );
-
-    udp_sock.connect(dns_server_addr)?;
-    tracing::trace!("Successfully connected to remote addr: {dns_server_addr}");
+/// The exit status reported for each kind of resolution failure, as listed in the help message
+fn exit_code(err: &ResolverError) -> ExitCode {
+    match err {
+        ResolverError::NxDomain { .. } => ExitCode::from(3),
+        ResolverError::NoData { .. } => ExitCode::from(4),
+        ResolverError::ServFail { .. } => ExitCode::from(5),
+        ResolverError::Refused { .. } => ExitCode::from(6),
+        ResolverError::ResponseCode { .. } => ExitCode::from(7),
+        ResolverError::Transport(_)
+        | ResolverError::NoNameservers
+        | ResolverError::InvalidReferral { .. }
+        | ResolverError::Loop { .. }
+        | ResolverError::QueryLimit { .. } => ExitCode::FAILURE,
+    }
+}
 
-    Ok(udp_sock)
+fn load_root_hints(path: &str) -> hints::Result<RootHints> {
+    RootHints::load(Path::new(path))
 }
 
-#[tracing::instrument(fields(question = query.get_query().qname.to_string()), skip(query))]
-fn send_query(query: Message, server_addr: std::net::IpAddr) -> MsgResult<Message> {
-    let socket_addr = std::net::SocketAddr::from((server_addr, 53));
+fn lookup_domain(args: &Arguments) -> ResolverResult<Lookup> {
+    let resolv_conf = load_resolv_conf(&args.resolv_conf);
 
-    // connection setup
-    let udp_sock = setup_udp_socket_to(socket_addr)?;
+    let mut resolver = Resolver::new(args.config(args.stub.then_some(&resolv_conf)));
+    if let Some(root_hints) = &args.root_hints {
+        resolver = resolver.with_root_hints(root_hints.clone());
+    }
+    if args.stub {
+        resolver = resolver.with_upstreams(resolv_conf.nameservers());
+    }
+    if !args.no_search {
+        resolver = resolver.with_search_list(resolv_conf.search_list());
+    }
 
-    // query request
-    tracing::trace!("Sending query for {:?} via {udp_sock:?}", query.get_query());
-    udp_sock.send(&query.query_into_bytes())?;
-    tracing::trace!("Query sent successfully");
+    let cache_path = Cache::default_path()
+        .filter(|_| !args.no_cache)
+        .map(|path| path.with_file_name(cache_file_name(args)));
+    if let Some(path) = &cache_path {
+        *resolver.cache() = load_cache(path);
+    }
 
-    // get response
-    let mut recv_buf = [0u8; 1024];
-    tracing::trace!("Beginning to parse response");
-    let bytes_recv = udp_sock.recv(&mut recv_buf)?;
-    tracing::trace!("Received {bytes_recv} bytes in response");
+    // refresh the root nameservers, unless a previous run already did
+    let root_ns = CacheKey::new(DomainName::root(), QType::NS, QClass::IN);
+    if !args.stub && resolver.cache().get(&root_ns, Instant::now()).is_none() {
+        if let Err(e) = resolver.prime() {
+            tracing::warn!("Failed to prime the root nameservers, using the hints as is: {e}");
+        }
+    }
 
-    // parse response to message
-    let mut msg_bytes_reader = Cursor::new(&recv_buf[..bytes_recv]);
+    let lookup = match args.ip_v6 {
+        true => resolver.resolve(&args.request, QType::AAAA),
+        false => resolver.resolve(&args.request, QType::A),
+    };
 
-    Message::from_bytes(&mut msg_bytes_reader)
+    if let Some(path) = &cache_path {
+        if let Err(e) = resolver.cache().save(path) {
+            tracing::warn!("Could not save the cache to {}: {e}", path.display());
+        }
+    }
+    lookup
 }
 
-fn lookup_domain(args: &Arguments) -> MsgResult<std::net::IpAddr> {
-    match args.ip_v6 {
-        true => resolve(&args.request, QType::AAAA),
-        false => resolve(&args.request, QType::A),
+/// Reads resolv.conf, falling back to the defaults of the C library if it cannot be read
+fn load_resolv_conf(path: &Path) -> ResolvConf {
+    match ResolvConf::load(path) {
+        Ok(resolv_conf) => resolv_conf,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            tracing::debug!("No {}, using the defaults", path.display());
+            ResolvConf::default()
+        }
+        Err(e) => {
+            tracing::warn!("Could not read {}, using the defaults: {e}", path.display());
+            ResolvConf::default()
+        }
     }
 }
 
-pub fn resolve(domain_name: &str, record_type: QType) -> MsgResult<std::net::IpAddr> {
-    let mut nameserver = match record_type {
-        QType::AAAA => std::net::IpAddr::V6("2001:503:ba3e::2:30".parse().unwrap()),
-        _ => std::net::IpAddr::V4(std::net::Ipv4Addr::new(198, 41, 0, 4)),
+/// The name of the file caching the names resolved through the nameservers in use.
+///
+/// Upstream nameservers may have their own view of names, and so may other root nameservers,
+/// which is kept apart: otherwise the root nameservers cached from the others would be asked instead.
+fn cache_file_name(args: &Arguments) -> String {
+    if args.stub {
+        return "stub-cache".to_string();
+    }
+    let Some(root_hints) = &args.root_hints else {
+        return "cache".to_string();
     };
-    loop {
-        tracing::info!("Querying {nameserver} for \"{domain_name}\"");
-        let query = Message::new_query(domain_name, record_type, false, false);
-
-        let resp = send_query(query, nameserver)?;
-
-        tracing::debug!("Received response: {:?}\n{:?}", resp.header, resp);
-
-        if let Some(domain_ip_rr) = resp.get_record_by_type_from(record_type, MsgSection::Answers) {
-            tracing::debug!(
-                "Found answer for \"{domain_name}\": {}",
-                domain_ip_rr.data_as_ip_addr()
-            );
-            return Ok(domain_ip_rr.data_as_ip_addr());
-        } else if let Some(ns_ip_rr) =
-            resp.get_record_by_type_from(record_type, MsgSection::Additionals)
-        {
-            nameserver = ns_ip_rr.data_as_ip_addr();
-            tracing::debug!("Referred to new nameserver: {nameserver}");
-        } else if let Some(ns_dname_rr) =
-            resp.get_record_by_type_from(QType::NS, MsgSection::Authorities)
-        {
-            tracing::debug!(
-                "Found name for new nameserver: \"{}\"",
-                ns_dname_rr.data_as_str()
-            );
-            nameserver = resolve(ns_dname_rr.data_as_str(), record_type)?;
-            tracing::debug!(
-                "Resolved new namserver \"{}\": {nameserver}",
-                ns_dname_rr.data_as_str()
-            );
-        } else if let Some(cname_rr) =
-            resp.get_record_by_type_from(QType::CNAME, MsgSection::Answers)
+
+    let mut hasher = DefaultHasher::new();
+    root_hints.addrs().for_each(|ip| ip.hash(&mut hasher));
+    format!("cache-{:016x}", hasher.finish())
+}
+
+/// Loads the cache saved by a previous run, starting afresh if there is none or it cannot be read
+fn load_cache(path: &Path) -> Cache {
+    match Cache::load(path) {
+        Ok(cache) => cache,
+        Err(dirt::resolver::cache::file::Error::Io(e))
+            if e.kind() == std::io::ErrorKind::NotFound =>
         {
-            tracing::debug!(
-                "Found alias \"{}\" for \"{domain_name}\"",
-                cname_rr.data_as_str()
-            );
-            return resolve(cname_rr.data_as_str(), record_type);
-        } else {
-            panic!("Unexpected resolver error\nreceived: {resp:#?}")
+            Cache::new()
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring the cache at {}: {e}", path.display());
+            Cache::new()
         }
     }
 }
@@ -143,12 +258,19 @@ pub fn resolve(domain_name: &str, record_type: QType) -> MsgResult<std::net::IpA
 mod tests {
     use crate::*;
 
+    use dirt::message::Message;
+
     #[test]
     fn test_build_query() -> std::fmt::Result {
         let correct_bytes_str =
             "82980100000100000000000003777777076578616d706c6503636f6d0000010001";
-        let query = Message::new_query("www.example.com", dirt::qtype::QType::A, false, true);
-        let query_bytes = query.query_into_bytes();
+        let query = Message::new_query(
+            DomainName::new("www.example.com"),
+            dirt::qtype::QType::A,
+            false,
+            true,
+        );
+        let query_bytes = query.query_into_bytes().unwrap();
 
         let mut query_bytes_str = String::with_capacity(correct_bytes_str.len());
 
@@ -169,43 +291,28 @@ mod tests {
     }
 
     #[test]
-    fn test_send_query() -> std::io::Result<()> {
-        let query = Message::new_query("www.example.com", dirt::qtype::QType::A, false, true);
-        let query_bytes = query.query_into_bytes();
-
-        // connection setup
-        let udp_sock =
-            setup_udp_socket_to("8.8.8.8:53".parse().unwrap()).expect("Failed to setup UDP socket");
-
-        // query request
-        udp_sock.send(&query_bytes).expect("Couldn't send query");
-
-        Ok(())
+    fn reject_zero_timeout() {
+        assert!(Arguments::try_parse_from(["dirt", "example.com", "--timeout", "0"]).is_err());
+        let args = Arguments::try_parse_from(["dirt", "example.com", "--timeout", "1"]).unwrap();
+        assert_eq!(args.timeout, Some(1));
+        assert!(Arguments::try_parse_from(["dirt", "example.com", "--deadline", "0"]).is_err());
     }
 
     #[test]
-    fn test_resolve() -> MsgResult<()> {
-        let result_ip = resolve("www.example.com", QType::A).expect("Failed to resolve");
-        let correct_ip = "93.184.216.34".parse::<std::net::Ipv4Addr>().unwrap();
-        assert_eq!(result_ip, correct_ip);
-        Ok(())
-    }
+    fn separate_cache_files() {
+        let mut args = Arguments::try_parse_from(["dirt", "example.com"]).unwrap();
+        assert_eq!(cache_file_name(&args), "cache");
 
-    #[test]
-    fn test_cname() -> MsgResult<()> {
-        // facebook has multiple IP addrs, no sense checking for any possible one.
-        let _ = resolve("www.facebook.com", QType::A).expect("Failed to lookup domain");
-        Ok(())
-    }
+        args.root_hints = Some(RootHints::default());
+        let default_hints = cache_file_name(&args);
+        assert_ne!(default_hints, "cache");
 
-    #[test]
-    #[ignore = "GitHub CI doesn't support IPv6.\nSee https://github.com/actions/runner-images/issues/668"]
-    fn test_resolve_ipv6() -> MsgResult<()> {
-        let result_ip = resolve("www.example.com", QType::AAAA).expect("Failed to resolve");
-        let correct_ip = "2606:2800:220:1:248:1893:25c8:1946"
-            .parse::<std::net::Ipv6Addr>()
-            .unwrap();
-        assert_eq!(result_ip, correct_ip);
-        Ok(())
+        let mut root_hints = RootHints::default();
+        root_hints.addr_records.truncate(1);
+        args.root_hints = Some(root_hints);
+        assert_ne!(cache_file_name(&args), default_hints);
+
+        args.stub = true;
+        assert_eq!(cache_file_name(&args), "stub-cache");
     }
 }
@@ -0,0 +1,1597 @@
+//! An iterative resolver, following referrals from the root nameservers down to an answer.
+//!
+//! See more in [RFC 1034 section 5.3.3](https://datatracker.ietf.org/doc/html/rfc1034#section-5.3.3)
+
+pub mod cache;
+pub mod hints;
+pub mod resolv_conf;
+pub mod transport;
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dname::DomainName,
+    header::ResponseCode,
+    message::{Message, MsgSection},
+    qclass::QClass,
+    qtype::QType,
+    question::Question,
+    rdata::RData,
+    record::Record,
+};
+
+pub use cache::{Cache, CacheKey, Cached};
+pub use hints::RootHints;
+pub use resolv_conf::{ResolvConf, SearchList};
+pub use transport::{NetworkTransport, Transport, DNS_PORT};
+
+/// Controls how long the resolver waits for responses, and how often it retries queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolverConfig {
+    /// How long to wait for a response to the first attempt of a query.
+    ///
+    /// The timeout doubles with each retry.
+    pub timeout: Duration,
+    /// How many times a query is retried, after the first attempt went unanswered by every candidate nameserver
+    pub retries: u32,
+    /// How long a query may wait for responses in total, across all its attempts and nameservers.
+    ///
+    /// Without it, a query to many unreachable nameservers would wait for each of them in every attempt.
+    pub deadline: Duration,
+    /// Which address family is used to reach nameservers, whatever the type of the records asked for
+    pub ip_preference: IpPreference,
+    /// How many queries a single resolution may send, including those needed to find nameservers and follow aliases
+    ///
+    /// Retries of a query are not counted.
+    pub max_queries: u32,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            retries: 2,
+            deadline: Duration::from_secs(30),
+            ip_preference: IpPreference::default(),
+            max_queries: 64,
+        }
+    }
+}
+
+/// The address families through which nameservers are reached, in order of preference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// IPv4 addresses first, then IPv6 ones
+    #[default]
+    Ipv4,
+    /// IPv6 addresses first, then IPv4 ones
+    Ipv6,
+    /// IPv4 addresses only, e.g. on hosts without IPv6 connectivity
+    Ipv4Only,
+    /// IPv6 addresses only
+    Ipv6Only,
+}
+
+impl IpPreference {
+    /// The types of the address records to look up for a nameserver, most preferred first
+    pub fn address_types(self) -> &'static [QType] {
+        match self {
+            Self::Ipv4 => &[QType::A, QType::AAAA],
+            Self::Ipv6 => &[QType::AAAA, QType::A],
+            Self::Ipv4Only => &[QType::A],
+            Self::Ipv6Only => &[QType::AAAA],
+        }
+    }
+
+    /// Whether nameservers may be reached at the address
+    pub fn allows(self, ip: IpAddr) -> bool {
+        match self {
+            Self::Ipv4 | Self::Ipv6 => true,
+            Self::Ipv4Only => ip.is_ipv4(),
+            Self::Ipv6Only => ip.is_ipv6(),
+        }
+    }
+
+    /// Turns the addresses into nameserver sockets, leaving out disallowed ones and moving preferred ones first
+    pub fn nameservers(self, ips: impl IntoIterator<Item = IpAddr>) -> Vec<SocketAddr> {
+        let mut ips: Vec<IpAddr> = ips.into_iter().filter(|&ip| self.allows(ip)).collect();
+        // the sort is stable, so addresses of the same family keep their order
+        ips.sort_by_key(|ip| match self {
+            Self::Ipv6 | Self::Ipv6Only => ip.is_ipv4(),
+            Self::Ipv4 | Self::Ipv4Only => ip.is_ipv6(),
+        });
+        ips.into_iter()
+            .map(|ip| SocketAddr::from((ip, DNS_PORT)))
+            .collect()
+    }
+}
+
+/// The outcome of a successful resolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup {
+    /// The question that was asked, holding the absolute name the requested one was qualified to
+    pub question: Question,
+    /// The CNAME records followed from the question's name to the canonical name, in order
+    pub cname_chain: Vec<Record>,
+    /// The RRset answering the question, owned by the canonical name
+    pub records: Vec<Record>,
+    /// The nameserver the records came from, or `None` if they were found in the cache
+    pub nameserver: Option<SocketAddr>,
+}
+
+impl Lookup {
+    /// The name owning the records, once every alias has been followed
+    pub fn canonical_name(&self) -> &DomainName {
+        &self.records[0].name
+    }
+
+    /// The time for which the whole answer may be cached, including the aliases leading to it
+    pub fn min_ttl(&self) -> u32 {
+        self.cname_chain
+            .iter()
+            .chain(&self.records)
+            .map(|rr| rr.time_to_live)
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// The addresses held by the records, if they are A or AAAA records
+    pub fn ip_addrs(&self) -> impl Iterator<Item = std::net::IpAddr> + '_ {
+        self.records.iter().filter_map(|rr| rr.rdata.as_ip_addr())
+    }
+}
+
+/// The work left for a single resolution, shared by the lookups nested within it
+#[derive(Debug)]
+struct Budget {
+    max_queries: u32,
+    queries_left: u32,
+    /// The lookups in progress, from the outermost one
+    lookups: Vec<(DomainName, QType)>,
+}
+
+impl Budget {
+    fn new(max_queries: u32) -> Self {
+        Self {
+            max_queries,
+            queries_left: max_queries,
+            lookups: vec![],
+        }
+    }
+
+    /// Starts a nested lookup, unless the same lookup is already in progress
+    fn enter(&mut self, name: &DomainName, record_type: QType) -> Result<()> {
+        let lookup = (name.clone(), record_type);
+        if self.lookups.contains(&lookup) {
+            return Err(Error::Loop {
+                name: lookup.0,
+                record_type,
+            });
+        }
+        self.lookups.push(lookup);
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.lookups.pop();
+    }
+
+    fn spend_query(&mut self) -> Result<()> {
+        self.queries_left = self.queries_left.checked_sub(1).ok_or(Error::QueryLimit {
+            max_queries: self.max_queries,
+        })?;
+        Ok(())
+    }
+}
+
+/// Resolves domain names by iterating from the root nameservers
+///
+/// What is learnt along the way is kept in a [`Cache`], shared by every resolution.
+#[derive(Debug, Default)]
+pub struct Resolver<T: Transport = NetworkTransport> {
+    config: ResolverConfig,
+    transport: T,
+    cache: Mutex<Cache>,
+    root_hints: RootHints,
+    /// The recursive nameservers that resolve names on our behalf, if any
+    upstreams: Vec<SocketAddr>,
+    search_list: SearchList,
+}
+
+impl Resolver {
+    /// Creates a resolver sending its queries over the network
+    pub fn new(config: ResolverConfig) -> Self {
+        Self::with_transport(config, NetworkTransport)
+    }
+}
+
+impl<T: Transport> Resolver<T> {
+    /// Creates a resolver sending its queries through the given transport
+    pub fn with_transport(config: ResolverConfig, transport: T) -> Self {
+        Self {
+            config,
+            transport,
+            cache: Mutex::default(),
+            root_hints: RootHints::default(),
+            upstreams: vec![],
+            search_list: SearchList::default(),
+        }
+    }
+
+    /// Qualifies relative names with the given search list before resolving them
+    pub fn with_search_list(mut self, search_list: SearchList) -> Self {
+        self.search_list = search_list;
+        self
+    }
+
+    pub fn search_list(&self) -> &SearchList {
+        &self.search_list
+    }
+
+    /// Sends every query to the given recursive nameservers, as a stub resolver, instead of iterating from the roots
+    pub fn with_upstreams(mut self, upstreams: Vec<SocketAddr>) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+
+    /// The recursive nameservers queries are sent to, if this is a stub resolver
+    pub fn upstreams(&self) -> &[SocketAddr] {
+        &self.upstreams
+    }
+
+    /// Starts resolutions from the given root nameservers, instead of the built-in ones
+    pub fn with_root_hints(mut self, root_hints: RootHints) -> Self {
+        self.root_hints = root_hints;
+        self
+    }
+
+    pub fn root_hints(&self) -> &RootHints {
+        &self.root_hints
+    }
+
+    pub fn config(&self) -> &ResolverConfig {
+        &self.config
+    }
+
+    /// Gives access to the resolver's cache
+    pub fn cache(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Finds the records of the given type for the domain name, following referrals and aliases.
+    ///
+    /// Relative names are qualified with the [`SearchList`] first, trying each candidate name in turn
+    /// until one of them has records of the type.
+    /// Candidates whose nameservers fail, do not respond or cannot be found are skipped as well.
+    /// The question of the returned [`Lookup`] holds the candidate that did.
+    pub fn resolve(&self, domain_name: &DomainName, record_type: QType) -> Result<Lookup> {
+        let mut err = Error::NoNameservers;
+        let mut nodata = None;
+        let mut failure = None;
+
+        for candidate in self.search_list.candidates(domain_name) {
+            let mut budget = Budget::new(self.config.max_queries);
+            match self.resolve_within(&candidate, record_type, &mut budget) {
+                Ok(lookup) => return Ok(lookup),
+                Err(e @ Error::NoData { .. }) if nodata.is_none() => nodata = Some(e),
+                Err(e @ (Error::NxDomain { .. } | Error::NoData { .. })) => err = e,
+                // the nameservers of this candidate failed, those of the next ones may not
+                Err(
+                    e @ (Error::ServFail { .. }
+                    | Error::Refused { .. }
+                    | Error::ResponseCode { .. }
+                    | Error::InvalidReferral { .. }
+                    | Error::NoNameservers
+                    | Error::Transport(_)),
+                ) => {
+                    tracing::warn!("Failed to resolve \"{candidate}\": {e}");
+                    failure.get_or_insert(e);
+                    continue;
+                }
+                // the name could exist, but it could not be found out
+                Err(e) => return Err(e),
+            }
+            tracing::debug!(
+                "\"{candidate}\" has no {record_type} records, trying the next candidate"
+            );
+        }
+
+        // a name that exists tells more than a failure, which tells more than the names that do not
+        Err(nodata.or(failure).unwrap_or(err))
+    }
+
+    /// Asks the root hints for the current root nameservers and caches them, as described in RFC 8109.
+    ///
+    /// Later resolutions start from the cached root nameservers, until they expire.
+    pub fn prime(&self) -> Result<Lookup> {
+        let root = DomainName::root();
+        let nameservers = self
+            .config
+            .ip_preference
+            .nameservers(self.root_hints.addrs());
+
+        tracing::info!("Priming the root nameservers from {nameservers:?}");
+        let query = Message::new_query(root.clone(), QType::NS, false, false);
+        let (resp, nameserver) = self.send_query(query, &nameservers)?;
+
+        let ns_records: Vec<Record> = resp
+            .get_rrset_from(&root, QType::NS, MsgSection::Answers)
+            .cloned()
+            .collect();
+        if ns_records.is_empty() {
+            return Err(Error::NoData {
+                name: root,
+                record_type: QType::NS,
+                soa: None,
+            });
+        }
+
+        // every name is within the root zone, so all the addresses of root nameservers are in bailiwick
+        let addr_records = resp
+            .get_records(MsgSection::Additionals)
+            .iter()
+            .filter(|rr| matches!(rr.qtype, QType::A | QType::AAAA))
+            .filter(|rr| {
+                ns_records
+                    .iter()
+                    .any(|ns| ns.rdata.as_domain_name() == Some(&rr.name))
+            })
+            .cloned();
+        self.cache().insert_records(
+            ns_records.iter().cloned().chain(addr_records),
+            Instant::now(),
+        );
+        tracing::debug!("Primed {} root nameservers", ns_records.len());
+
+        Ok(Lookup {
+            question: resp.get_query().clone(),
+            cname_chain: vec![],
+            records: ns_records,
+            nameserver: Some(nameserver),
+        })
+    }
+
+    /// Resolves the domain name as part of a larger resolution, sharing its budget
+    fn resolve_within(
+        &self,
+        domain_name: &DomainName,
+        record_type: QType,
+        budget: &mut Budget,
+    ) -> Result<Lookup> {
+        budget.enter(domain_name, record_type)?;
+        let result = match self.upstreams.is_empty() {
+            true => self.resolve_iteratively(domain_name, record_type, budget),
+            false => self.resolve_stub(domain_name, record_type, budget),
+        };
+        budget.leave();
+        result
+    }
+
+    fn resolve_iteratively(
+        &self,
+        domain_name: &DomainName,
+        record_type: QType,
+        budget: &mut Budget,
+    ) -> Result<Lookup> {
+        if let Some(result) = self.resolve_cached(domain_name, record_type, budget) {
+            return result;
+        }
+
+        let ip_preference = self.config.ip_preference;
+        // the zone the nameservers are authoritative for, which bounds what they can tell about
+        let (mut zone, mut nameservers) =
+            self.cached_nameservers(domain_name).unwrap_or_else(|| {
+                let root_servers = ip_preference.nameservers(self.root_hints.addrs());
+                (DomainName::root(), root_servers)
+            });
+        loop {
+            tracing::info!("Querying {nameservers:?} for \"{domain_name}\"");
+            let query = Message::new_query(domain_name.clone(), record_type, false, false);
+
+            budget.spend_query()?;
+            let (resp, nameserver) = self.send_query(query, &nameservers)?;
+
+            tracing::debug!("Received response: {:?}\n{:?}", resp.header, resp);
+
+            let (mut cname_chain, records, name) =
+                follow_aliases(&resp, domain_name, record_type, &zone);
+
+            let now = Instant::now();
+            self.cache()
+                .insert_records(cname_chain.iter().chain(&records).cloned(), now);
+
+            if !records.is_empty() {
+                tracing::debug!("Found {} answers for \"{name}\"", records.len());
+                return Ok(Lookup {
+                    question: resp.get_query().clone(),
+                    cname_chain,
+                    records,
+                    nameserver: Some(nameserver),
+                });
+            }
+
+            let soa = resp
+                .get_record_by_type_from(QType::SOA, MsgSection::Authorities)
+                .cloned();
+
+            // the response code refers to the last name of the chain, which may lie outside of the zone
+            if resp.header.flags.response_code() == ResponseCode::NxDomain
+                && name.is_subdomain_of(&zone)
+            {
+                return Err(self.nxdomain(name, soa, now));
+            }
+
+            if !cname_chain.is_empty() {
+                // the canonical name's records live elsewhere, start over from the root
+                let mut lookup = self.resolve_within(name, record_type, budget)?;
+                cname_chain.append(&mut lookup.cname_chain);
+                lookup.cname_chain = cname_chain;
+                lookup.question = resp.get_query().clone();
+                return Ok(lookup);
+            }
+
+            let referral: Vec<&Record> = resp
+                .get_records_by_type_from(QType::NS, MsgSection::Authorities)
+                .collect();
+
+            // the nameservers of the zone may list themselves along with the absence of data,
+            // while a referral back to the zone itself is neither authoritative nor carries its SOA
+            let lists_itself = referral.iter().all(|ns| ns.name == zone)
+                && (resp.header.flags.authoritative() || soa.is_some());
+            if referral.is_empty() || lists_itself {
+                // neither an answer nor a referral: the name exists without records of this type
+                return Err(self.nodata(domain_name, record_type, soa, now));
+            }
+
+            let (ns_records, glue_records) = check_referral(&resp, &zone, domain_name);
+            let Some(zone_cut) = ns_records.first().map(|rr| rr.name.clone()) else {
+                // a single lame nameserver should not fail the resolution, ask the others instead
+                nameservers.retain(|&addr| addr != nameserver);
+                if nameservers.is_empty() {
+                    return Err(Error::InvalidReferral { nameserver });
+                }
+                tracing::warn!("Dropped {nameserver} after its invalid referral");
+                continue;
+            };
+            tracing::debug!("Referred from \"{zone}\" down to \"{zone_cut}\"");
+            zone = zone_cut;
+
+            // remember the delegation, along with its glue
+            self.cache()
+                .insert_records(ns_records.iter().chain(&glue_records).cloned(), now);
+
+            let glue_addrs = ip_preference
+                .nameservers(glue_records.iter().filter_map(|rr| rr.rdata.as_ip_addr()));
+
+            if !glue_addrs.is_empty() {
+                nameservers = glue_addrs;
+                tracing::debug!("Referred to new nameservers: {nameservers:?}");
+            } else {
+                nameservers = self.resolve_nameservers(&ns_records, budget)?;
+                tracing::debug!("Resolved new nameservers: {nameservers:?}");
+            }
+        }
+    }
+
+    /// Asks the upstream nameservers to resolve the name on our behalf
+    fn resolve_stub(
+        &self,
+        domain_name: &DomainName,
+        record_type: QType,
+        budget: &mut Budget,
+    ) -> Result<Lookup> {
+        if let Some(result) = self.resolve_cached(domain_name, record_type, budget) {
+            return result;
+        }
+
+        tracing::info!("Querying {:?} for \"{domain_name}\"", self.upstreams);
+        let query = Message::new_query(domain_name.clone(), record_type, false, true);
+
+        budget.spend_query()?;
+        let (resp, nameserver) = self.send_query(query, &self.upstreams)?;
+
+        tracing::debug!("Received response: {:?}\n{:?}", resp.header, resp);
+        if !resp.header.flags.recursion_avail() {
+            tracing::warn!("{nameserver} does not offer recursion");
+        }
+
+        // the upstream nameserver already followed the aliases it could, and may vouch for any name
+        let (cname_chain, records, name) =
+            follow_aliases(&resp, domain_name, record_type, &DomainName::root());
+
+        let now = Instant::now();
+        self.cache()
+            .insert_records(cname_chain.iter().chain(&records).cloned(), now);
+
+        if !records.is_empty() {
+            tracing::debug!("Found {} answers for \"{name}\"", records.len());
+            return Ok(Lookup {
+                question: resp.get_query().clone(),
+                cname_chain,
+                records,
+                nameserver: Some(nameserver),
+            });
+        }
+
+        let soa = resp
+            .get_record_by_type_from(QType::SOA, MsgSection::Authorities)
+            .cloned();
+        match resp.header.flags.response_code() {
+            ResponseCode::NxDomain => Err(self.nxdomain(name, soa, now)),
+            _ => Err(self.nodata(name, record_type, soa, now)),
+        }
+    }
+
+    /// Caches the nonexistence of the name, if the SOA record of its zone allows it
+    fn nxdomain(&self, name: &DomainName, soa: Option<Record>, now: Instant) -> Error {
+        tracing::debug!("\"{name}\" does not exist");
+        if let Some(soa) = &soa {
+            self.cache()
+                .insert_nxdomain(name.clone(), QClass::IN, soa.clone(), now);
+        }
+        Error::NxDomain {
+            name: name.clone(),
+            soa: soa.map(Box::new),
+        }
+    }
+
+    /// Caches the absence of records of the type, if the SOA record of the name's zone allows it
+    fn nodata(
+        &self,
+        name: &DomainName,
+        record_type: QType,
+        soa: Option<Record>,
+        now: Instant,
+    ) -> Error {
+        tracing::debug!("\"{name}\" has no {record_type} records");
+        if let Some(soa) = &soa {
+            let key = CacheKey::new(name.clone(), record_type, QClass::IN);
+            self.cache().insert_nodata(key, soa.clone(), now);
+        }
+        Error::NoData {
+            name: name.clone(),
+            record_type,
+            soa: soa.map(Box::new),
+        }
+    }
+
+    /// Finds the addresses of the first of the nameservers whose name resolves,
+    /// trying each allowed address family in order of preference
+    fn resolve_nameservers(
+        &self,
+        ns_records: &[Record],
+        budget: &mut Budget,
+    ) -> Result<Vec<SocketAddr>> {
+        let ip_preference = self.config.ip_preference;
+        let mut err = Error::NoNameservers;
+
+        for ns_dname in ns_records.iter().filter_map(|rr| rr.rdata.as_domain_name()) {
+            tracing::debug!("Found name for new nameserver: \"{ns_dname}\"");
+            for &address_type in ip_preference.address_types() {
+                match self.resolve_within(ns_dname, address_type, budget) {
+                    Ok(lookup) => return Ok(ip_preference.nameservers(lookup.ip_addrs())),
+                    Err(e @ Error::QueryLimit { .. }) => return Err(e),
+                    Err(e) => {
+                        tracing::warn!("Failed to resolve \"{ns_dname}\" {address_type}: {e}");
+                        // a loop tells more about why no nameserver could be found than their absence
+                        if matches!(e, Error::Loop { .. }) {
+                            err = e;
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(err)
+    }
+
+    /// Answers from the cache, if it holds the records, an alias to follow, or a negative answer
+    fn resolve_cached(
+        &self,
+        domain_name: &DomainName,
+        record_type: QType,
+        budget: &mut Budget,
+    ) -> Option<Result<Lookup>> {
+        let now = Instant::now();
+        let question = Question {
+            qname: domain_name.clone(),
+            qtype: record_type,
+            qclass: QClass::IN,
+        };
+
+        let key = CacheKey::new(domain_name.clone(), record_type, QClass::IN);
+        let cached = self.cache().get(&key, now);
+        match cached {
+            Some(Cached::Records(records)) => {
+                tracing::debug!(
+                    "Found {} cached answers for \"{domain_name}\"",
+                    records.len()
+                );
+                return Some(Ok(Lookup {
+                    question,
+                    cname_chain: vec![],
+                    records,
+                    nameserver: None,
+                }));
+            }
+            Some(Cached::NxDomain(soa)) => {
+                tracing::debug!("\"{domain_name}\" is cached as nonexistent");
+                return Some(Err(Error::NxDomain {
+                    name: domain_name.clone(),
+                    soa: Some(Box::new(soa)),
+                }));
+            }
+            Some(Cached::NoData(soa)) => {
+                tracing::debug!("\"{domain_name}\" is cached as having no {record_type} records");
+                return Some(Err(Error::NoData {
+                    name: domain_name.clone(),
+                    record_type,
+                    soa: Some(Box::new(soa)),
+                }));
+            }
+            None => {}
+        }
+
+        if record_type == QType::CNAME {
+            return None;
+        }
+
+        let cname_key = CacheKey::new(domain_name.clone(), QType::CNAME, QClass::IN);
+        let cname_rr = self
+            .cache()
+            .get_records(&cname_key, now)?
+            .into_iter()
+            .next()?;
+        let cname = cname_rr.rdata.as_domain_name()?.clone();
+        tracing::debug!("Found cached alias \"{cname}\" for \"{domain_name}\"");
+
+        Some(
+            self.resolve_within(&cname, record_type, budget)
+                .map(|mut lookup| {
+                    lookup.cname_chain.insert(0, cname_rr);
+                    lookup.question = question;
+                    lookup
+                }),
+        )
+    }
+
+    /// Returns the closest enclosing zone whose nameservers' addresses are cached, along with these addresses
+    fn cached_nameservers(
+        &self,
+        domain_name: &DomainName,
+    ) -> Option<(DomainName, Vec<SocketAddr>)> {
+        let now = Instant::now();
+        let cache = self.cache();
+        let ip_preference = self.config.ip_preference;
+
+        std::iter::successors(Some(domain_name.clone()), DomainName::parent).find_map(|zone| {
+            let ns_key = CacheKey::new(zone.clone(), QType::NS, QClass::IN);
+            let ns_records = cache.get_records(&ns_key, now)?;
+            let addrs = ip_preference.nameservers(
+                ns_records
+                    .iter()
+                    .filter_map(|rr| rr.rdata.as_domain_name())
+                    .flat_map(|ns_dname| {
+                        ip_preference
+                            .address_types()
+                            .iter()
+                            .filter_map(|&address_type| {
+                                let addr_key =
+                                    CacheKey::new(ns_dname.clone(), address_type, QClass::IN);
+                                cache.get_records(&addr_key, now)
+                            })
+                    })
+                    .flatten()
+                    .filter_map(|rr| rr.rdata.as_ip_addr()),
+            );
+
+            (!addrs.is_empty()).then(|| {
+                tracing::debug!("Starting from the cached nameservers of \"{zone}\"");
+                (zone, addrs)
+            })
+        })
+    }
+
+    /// Sends the query to each of the nameservers in turn, until one of them responds.
+    ///
+    /// Responses reporting a failure of the nameserver, rather than of the name,
+    /// are treated like missing responses.
+    /// Each round over the nameservers waits twice as long for responses as the previous one,
+    /// and the query gives up once its deadline passes.
+    #[tracing::instrument(
+        fields(question = query.get_query().qname.to_string()),
+        skip(self, query)
+    )]
+    fn send_query(
+        &self,
+        query: Message,
+        nameservers: &[SocketAddr],
+    ) -> Result<(Message, SocketAddr)> {
+        let mut last_err = Error::NoNameservers;
+        let deadline = Instant::now() + self.config.deadline;
+        for attempt in 0..=self.config.retries {
+            let timeout = self
+                .config
+                .timeout
+                .saturating_mul(2u32.saturating_pow(attempt));
+
+            for &nameserver in nameservers {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    tracing::warn!("Giving up after {:?}", self.config.deadline);
+                    return Err(last_err);
+                }
+
+                let timeout = timeout.min(remaining);
+                let err = match self.transport.query(query.clone(), nameserver, timeout) {
+                    // codes extended by EDNS(0), as BADVERS, are errors whatever the header says
+                    Ok(resp) => match (resp.response_code(), resp.header.flags.response_code()) {
+                        (code @ 0x10.., _) => Error::ResponseCode { code, nameserver },
+                        (_, ResponseCode::NoError | ResponseCode::NxDomain) => {
+                            return Ok((resp, nameserver))
+                        }
+                        (_, ResponseCode::ServFail) => Error::ServFail { nameserver },
+                        (_, ResponseCode::Refused) => Error::Refused { nameserver },
+                        (code, _) => Error::ResponseCode { code, nameserver },
+                    },
+                    Err(e) => e.into(),
+                };
+                tracing::warn!("Attempt {} to {nameserver} failed: {err}", attempt + 1);
+                last_err = err;
+            }
+        }
+
+        Err(last_err)
+    }
+}
+
+/// Follows the aliases found in the answers, from `domain_name` towards the requested records.
+///
+/// Only records within `zone` are used, as its nameservers cannot vouch for others:
+/// the chain stops at the first alias leading out of the zone.
+/// Returns the aliases that were followed, the records of the last name of the chain, if any, and that name.
+fn follow_aliases<'a>(
+    resp: &'a Message,
+    domain_name: &'a DomainName,
+    record_type: QType,
+    zone: &DomainName,
+) -> (Vec<Record>, Vec<Record>, &'a DomainName) {
+    let mut name = domain_name;
+    let mut cname_chain: Vec<Record> = vec![];
+    loop {
+        if !name.is_subdomain_of(zone) {
+            tracing::debug!("\"{name}\" lies outside of \"{zone}\", ignoring its answers");
+            return (cname_chain, vec![], name);
+        }
+
+        let records: Vec<Record> = resp
+            .get_rrset_from(name, record_type, MsgSection::Answers)
+            .cloned()
+            .collect();
+        // each alias can only be followed once
+        if !records.is_empty() || cname_chain.len() == resp.answers.len() {
+            return (cname_chain, records, name);
+        }
+
+        let Some((cname_rr, cname)) = resp
+            .get_rrset_from(name, QType::CNAME, MsgSection::Answers)
+            .find_map(|rr| match &rr.rdata {
+                RData::CNAME(cname) => Some((rr, cname)),
+                _ => None,
+            })
+        else {
+            return (cname_chain, records, name);
+        };
+        tracing::debug!("Found alias \"{cname}\" for \"{name}\"");
+        cname_chain.push(cname_rr.clone());
+        name = cname;
+    }
+}
+
+/// Keeps the NS records and glue of a referral that the nameservers of `zone` may vouch for.
+///
+/// The NS records must delegate a zone below `zone`, on the way to `qname`, and all delegate the same zone.
+/// Glue must belong to one of these nameservers, and lie within `zone`.
+/// Anything else could redirect the resolution to a server of an attacker's choosing, so it is dropped.
+fn check_referral(
+    resp: &Message,
+    zone: &DomainName,
+    qname: &DomainName,
+) -> (Vec<Record>, Vec<Record>) {
+    let mut zone_cut: Option<&DomainName> = None;
+    let mut ns_records = vec![];
+    for ns in resp.get_records_by_type_from(QType::NS, MsgSection::Authorities) {
+        let in_bailiwick = qname.is_subdomain_of(&ns.name)
+            && ns.name.is_subdomain_of(zone)
+            && ns.name != *zone
+            && zone_cut.is_none_or(|cut| ns.name == *cut);
+        if in_bailiwick {
+            zone_cut = Some(&ns.name);
+            ns_records.push(ns.clone());
+        } else {
+            tracing::warn!(
+                "Rejected NS record of \"{}\" from the nameservers of \"{zone}\", looking up \"{qname}\"",
+                ns.name
+            );
+        }
+    }
+
+    let glue_records = resp
+        .get_records(MsgSection::Additionals)
+        .iter()
+        .filter(|rr| matches!(rr.qtype, QType::A | QType::AAAA))
+        .filter(|rr| {
+            let in_bailiwick = rr.name.is_subdomain_of(zone)
+                && ns_records
+                    .iter()
+                    .any(|ns| ns.rdata.as_domain_name() == Some(&rr.name));
+            if !in_bailiwick {
+                tracing::warn!(
+                    "Rejected {} glue for \"{}\" from the nameservers of \"{zone}\"",
+                    rr.qtype,
+                    rr.name
+                );
+            }
+            in_bailiwick
+        })
+        .cloned()
+        .collect();
+
+    (ns_records, glue_records)
+}
+
+/// Wraps the errors that may be encountered while resolving a domain name
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Encountered while exchanging messages with a nameserver
+    #[error(transparent)]
+    Transport(#[from] transport::Error),
+    /// There was no nameserver to send the query to
+    #[error("No nameservers to query")]
+    NoNameservers,
+    /// The domain name does not exist
+    ///
+    /// Holds the SOA record of the zone that denied its existence, if it was provided.
+    #[error("Nonexistent domain \"{name}\"")]
+    NxDomain {
+        name: DomainName,
+        soa: Option<Box<Record>>,
+    },
+    /// The domain name exists, but has no records of the requested type
+    ///
+    /// Holds the SOA record of the zone holding the name, if it was provided.
+    #[error("\"{name}\" has no {record_type} records")]
+    NoData {
+        name: DomainName,
+        record_type: QType,
+        soa: Option<Box<Record>>,
+    },
+    /// Resolving the domain name required resolving it again, e.g. through a loop of aliases
+    #[error("Resolving \"{name}\" {record_type} loops back on itself")]
+    Loop {
+        name: DomainName,
+        record_type: QType,
+    },
+    /// The resolution sent as many queries as it may, without finding an answer
+    #[error("Gave up after sending {max_queries} queries")]
+    QueryLimit { max_queries: u32 },
+    /// The nameserver referred to nameservers it has no authority to vouch for
+    #[error("Invalid referral from {nameserver}")]
+    InvalidReferral { nameserver: SocketAddr },
+    /// The nameserver was unable to process the query
+    #[error("Server failure at {nameserver}")]
+    ServFail { nameserver: SocketAddr },
+    /// The nameserver refused to process the query
+    #[error("Query refused by {nameserver}")]
+    Refused { nameserver: SocketAddr },
+    /// The nameserver responded with any other error
+    ///
+    /// Holds the full 12-bit RCODE, including the bits carried by EDNS(0).
+    #[error("{nameserver} responded with RCODE {code}")]
+    ResponseCode { code: u16, nameserver: SocketAddr },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::convert::fixtures::record;
+    use mock::{mock_internet, soa, MockTransport, MockZone};
+
+    fn mock_resolver() -> Resolver<MockTransport> {
+        Resolver::with_transport(ResolverConfig::default(), mock_internet())
+    }
+
+    #[test]
+    fn test_mock_resolve() -> Result<()> {
+        let resolver = mock_resolver();
+
+        let lookup = resolver.resolve(&DomainName::new("www.example.com"), QType::A)?;
+        assert_eq!(
+            lookup.ip_addrs().next(),
+            Some(IpAddr::from([93, 184, 216, 34]))
+        );
+        assert_eq!(lookup.nameserver, "199.43.135.53:53".parse().ok());
+
+        let lookup = resolver.resolve(&DomainName::new("www.example.com"), QType::AAAA)?;
+        assert_eq!(
+            lookup.ip_addrs().next(),
+            "2606:2800:220:1:248:1893:25c8:1946".parse().ok()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_rrset() -> Result<()> {
+        let lookup = mock_resolver().resolve(&DomainName::new("www.example.com"), QType::A)?;
+
+        let ips: Vec<IpAddr> = lookup.ip_addrs().collect();
+        assert_eq!(
+            ips,
+            [
+                IpAddr::from([93, 184, 216, 34]),
+                IpAddr::from([93, 184, 216, 35])
+            ]
+        );
+        assert!(lookup.cname_chain.is_empty());
+        assert_eq!(lookup.min_ttl(), 3600);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_cname() -> Result<()> {
+        let lookup = mock_resolver().resolve(&DomainName::new("alias.example.com"), QType::A)?;
+
+        assert_eq!(lookup.question.qname, DomainName::new("alias.example.com"));
+        assert_eq!(lookup.canonical_name(), &DomainName::new("www.example.com"));
+        assert_eq!(lookup.cname_chain.len(), 1);
+        assert_eq!(lookup.records.len(), 2);
+        assert_eq!(lookup.min_ttl(), 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_cname_across_zones() -> Result<()> {
+        let lookup = mock_resolver().resolve(&DomainName::new("cdn.example.com"), QType::A)?;
+
+        assert_eq!(lookup.question.qname, DomainName::new("cdn.example.com"));
+        assert_eq!(
+            lookup.canonical_name(),
+            &DomainName::new("www.glueless.com")
+        );
+        assert_eq!(
+            lookup.cname_chain[0].name,
+            DomainName::new("cdn.example.com")
+        );
+        assert_eq!(lookup.ip_addrs().next(), Some(IpAddr::from([192, 0, 2, 1])));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_glueless_referral() -> Result<()> {
+        let lookup = mock_resolver().resolve(&DomainName::new("www.glueless.com"), QType::A)?;
+        assert_eq!(lookup.ip_addrs().next(), Some(IpAddr::from([192, 0, 2, 1])));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ip_preference() -> Result<()> {
+        let resolver = |ip_preference| {
+            let config = ResolverConfig {
+                ip_preference,
+                ..Default::default()
+            };
+            Resolver::with_transport(config, mock_internet())
+        };
+        let www = DomainName::new("www.example.com");
+
+        // the address family of the nameservers does not depend on the question
+        let lookup = resolver(IpPreference::Ipv4).resolve(&www, QType::AAAA)?;
+        assert_eq!(lookup.nameserver, "199.43.135.53:53".parse().ok());
+        let lookup = resolver(IpPreference::Ipv6Only).resolve(&www, QType::A)?;
+        assert_eq!(lookup.nameserver, "[2001:500:8f::53]:53".parse().ok());
+
+        // ns.example.com only has an IPv4 address
+        let glueless = DomainName::new("www.glueless.com");
+        let lookup = resolver(IpPreference::Ipv6).resolve(&glueless, QType::AAAA);
+        assert!(matches!(lookup, Err(Error::NoData { .. })));
+        assert_eq!(
+            lookup.unwrap_err().to_string(),
+            "\"www.glueless.com.\" has no AAAA records"
+        );
+        assert!(matches!(
+            resolver(IpPreference::Ipv6Only).resolve(&glueless, QType::A),
+            Err(Error::NoNameservers)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ip_preference_order() {
+        let ips = [
+            IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]),
+            IpAddr::from([192, 0, 2, 1]),
+            IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 2]),
+        ];
+        let order = |ip_preference: IpPreference| -> Vec<IpAddr> {
+            ip_preference
+                .nameservers(ips)
+                .iter()
+                .map(SocketAddr::ip)
+                .collect()
+        };
+
+        assert_eq!(order(IpPreference::Ipv4), [ips[1], ips[0], ips[2]]);
+        assert_eq!(order(IpPreference::Ipv6), [ips[0], ips[2], ips[1]]);
+        assert_eq!(order(IpPreference::Ipv4Only), [ips[1]]);
+        assert_eq!(order(IpPreference::Ipv6Only), [ips[0], ips[2]]);
+    }
+
+    #[test]
+    fn test_bailiwick() -> Result<()> {
+        let transport = mock_internet().forging(
+            &["192.5.6.30", "2001:503:a83e::2:30"],
+            vec![
+                // neither on the way to the name, nor below com.
+                record("org", RData::NS(DomainName::new("ns.evil.org"))),
+                record("com", RData::NS(DomainName::new("ns.evil.com"))),
+                // glue for servers that were not referred to, or outside of com.
+                record("ns.evil.com", RData::A([192, 0, 2, 66].into())),
+                record("a.iana-servers.net", RData::A([192, 0, 2, 66].into())),
+            ],
+        );
+        let resolver = Resolver::with_transport(ResolverConfig::default(), transport);
+
+        let lookup = resolver.resolve(&DomainName::new("www.example.com"), QType::A)?;
+        assert_eq!(lookup.nameserver, "199.43.135.53:53".parse().ok());
+
+        let now = Instant::now();
+        let cache = resolver.cache();
+        let key = |name, qtype| CacheKey::new(DomainName::new(name), qtype, QClass::IN);
+        assert_eq!(cache.get_records(&key("org", QType::NS), now), None);
+        assert_eq!(cache.get_records(&key("ns.evil.com", QType::A), now), None);
+        let ns_addrs = cache
+            .get_records(&key("a.iana-servers.net", QType::A), now)
+            .unwrap();
+        assert_eq!(ns_addrs[0].rdata, RData::A([199, 43, 135, 53].into()));
+        let com_ns = cache.get_records(&key("com", QType::NS), now).unwrap();
+        assert_eq!(com_ns.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_answer_bailiwick() -> Result<()> {
+        // the nameservers of example.com. answer for a name of glueless.com. along with their alias
+        let transport = mock_internet().forging(
+            &["199.43.135.53", "2001:500:8f::53"],
+            vec![record("www.glueless.com", RData::A([192, 0, 2, 66].into()))],
+        );
+        let resolver = Resolver::with_transport(ResolverConfig::default(), transport);
+
+        let lookup = resolver.resolve(&DomainName::new("cdn.example.com"), QType::A)?;
+        assert_eq!(
+            lookup.canonical_name(),
+            &DomainName::new("www.glueless.com")
+        );
+        assert_eq!(
+            lookup.ip_addrs().collect::<Vec<_>>(),
+            [IpAddr::from([192, 0, 2, 1])]
+        );
+        assert_eq!(lookup.nameserver, "203.0.113.53:53".parse().ok());
+
+        let key = CacheKey::new(DomainName::new("www.glueless.com"), QType::A, QClass::IN);
+        let cached = resolver.cache().get_records(&key, Instant::now()).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].rdata, RData::A([192, 0, 2, 1].into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_referral() {
+        // a com. nameserver referring back to com. itself
+        let transport = mock_internet().serve(
+            &["192.5.6.30", "2001:503:a83e::2:30"],
+            MockZone::new(
+                ".",
+                vec![
+                    record("com", RData::NS(DomainName::new("ns.evil.com"))),
+                    record("ns.evil.com", RData::A([192, 0, 2, 66].into())),
+                ],
+            ),
+        );
+        let result = Resolver::with_transport(ResolverConfig::default(), transport)
+            .resolve(&DomainName::new("www.example.com"), QType::A);
+
+        assert!(matches!(result, Err(Error::InvalidReferral { .. })));
+    }
+
+    #[test]
+    fn test_invalid_referral_fallback() -> Result<()> {
+        // only the first com. nameserver refers back to com. itself
+        let transport = mock_internet().serve(
+            &["192.5.6.30"],
+            MockZone::new(
+                ".",
+                vec![record("com", RData::NS(DomainName::new("ns.evil.com")))],
+            ),
+        );
+        let lookup = Resolver::with_transport(ResolverConfig::default(), transport)
+            .resolve(&DomainName::new("www.example.com"), QType::A)?;
+
+        assert_eq!(lookup.records[0].rdata, RData::A([93, 184, 216, 34].into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cname_loop() {
+        let result = mock_resolver().resolve(&DomainName::new("loop.example.com"), QType::A);
+
+        let Err(Error::Loop { name, record_type }) = result else {
+            panic!("expected a loop, got {result:?}");
+        };
+        assert_eq!(name, DomainName::new("loop.example.com"));
+        assert_eq!(record_type, QType::A);
+    }
+
+    #[test]
+    fn test_nameserver_loop() {
+        let resolver = mock_resolver();
+        let result = resolver.resolve(&DomainName::new("www.selfish.com"), QType::A);
+
+        assert!(matches!(result, Err(Error::Loop { .. })), "got {result:?}");
+        // nothing is left in progress once the resolution gave up
+        assert!(resolver
+            .resolve(&DomainName::new("www.example.com"), QType::A)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_query_limit() {
+        let config = ResolverConfig {
+            max_queries: 3,
+            ..Default::default()
+        };
+        let resolver = Resolver::with_transport(config, mock_internet());
+        let result = resolver.resolve(&DomainName::new("www.example.com"), QType::A);
+
+        // the answer takes five: the root, com., then the root and net. for example.com.'s nameserver, and example.com.
+        assert!(matches!(result, Err(Error::QueryLimit { max_queries: 3 })));
+        assert_eq!(resolver.transport.queries_sent(), 3);
+    }
+
+    #[test]
+    fn test_root_hints() -> Result<()> {
+        // the first root does not answer
+        let hints = RootHints::parse(
+            ". NS a.root-servers.net.\n\
+             . NS b.root-servers.net.\n\
+             a.root-servers.net. A 192.0.2.1\n\
+             b.root-servers.net. A 198.41.0.4\n",
+        )
+        .unwrap();
+        let resolver = mock_resolver().with_root_hints(hints);
+
+        let lookup = resolver.resolve(&DomainName::new("www.example.com"), QType::A)?;
+        assert_eq!(
+            lookup.ip_addrs().next(),
+            Some(IpAddr::from([93, 184, 216, 34]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_priming() -> Result<()> {
+        let hints =
+            RootHints::parse(". NS a.root-servers.net.\na.root-servers.net. A 198.41.0.4").unwrap();
+        let resolver = mock_resolver().with_root_hints(hints);
+
+        let lookup = resolver.prime()?;
+        assert_eq!(lookup.records.len(), 1);
+        assert_eq!(lookup.nameserver, "198.41.0.4:53".parse().ok());
+
+        // the root nameservers' addresses now come from the priming response
+        let (zone, nameservers) = resolver.cached_nameservers(&DomainName::root()).unwrap();
+        assert!(zone.is_root());
+        assert_eq!(
+            nameservers,
+            [
+                "198.41.0.4:53".parse().unwrap(),
+                "[2001:503:ba3e::2:30]:53".parse().unwrap()
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stub_resolver() -> Result<()> {
+        let upstream = MockZone::recursive(vec![
+            record(
+                "intranet.corp.example",
+                RData::CNAME(DomainName::new("web.corp.example")),
+            ),
+            record("web.corp.example", RData::A([10, 1, 2, 3].into())),
+            record("corp.example", soa("corp.example")),
+        ]);
+        // the first upstream is down
+        let transport = MockTransport::default().serve(&["10.0.0.53"], upstream);
+        let resolver = Resolver::with_transport(ResolverConfig::default(), transport)
+            .with_upstreams(vec![
+                "10.0.0.1:53".parse().unwrap(),
+                "10.0.0.53:53".parse().unwrap(),
+            ]);
+
+        let lookup = resolver.resolve(&DomainName::new("intranet.corp.example"), QType::A)?;
+        assert_eq!(lookup.ip_addrs().next(), Some(IpAddr::from([10, 1, 2, 3])));
+        assert_eq!(lookup.cname_chain.len(), 1);
+        assert_eq!(lookup.nameserver, "10.0.0.53:53".parse().ok());
+
+        assert!(matches!(
+            resolver.resolve(&DomainName::new("missing.corp.example"), QType::A),
+            Err(Error::NxDomain { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve(&DomainName::new("web.corp.example"), QType::MX),
+            Err(Error::NoData { .. })
+        ));
+        // negative answers are cached as well
+        let queries_sent = resolver.transport.queries_sent();
+        assert!(resolver
+            .resolve(&DomainName::new("missing.corp.example"), QType::A)
+            .is_err());
+        assert_eq!(resolver.transport.queries_sent(), queries_sent);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_list() -> Result<()> {
+        let search_list = SearchList {
+            domains: vec![
+                DomainName::new("corp.example."),
+                DomainName::new("glueless.com."),
+                DomainName::new("example.com."),
+            ],
+            ndots: 1,
+        };
+        let resolver = mock_resolver().with_search_list(search_list);
+
+        // www.corp.example does not exist, and www.example.com is not tried
+        let lookup = resolver.resolve(&DomainName::new("www"), QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.glueless.com"));
+        assert_eq!(lookup.ip_addrs().next(), Some(IpAddr::from([192, 0, 2, 1])));
+
+        // names with enough dots are tried as is first
+        let lookup = resolver.resolve(&DomainName::new("www.example.com"), QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.example.com"));
+
+        let result = resolver.resolve(&DomainName::new("alias"), QType::MX);
+        let Err(Error::NoData { name, .. }) = result else {
+            panic!("expected NODATA, got {result:?}");
+        };
+        assert_eq!(name, DomainName::new("www.example.com"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_list_failures() -> Result<()> {
+        let resolver = |transport, domains: &[&str]| {
+            let config = ResolverConfig {
+                retries: 0,
+                ..Default::default()
+            };
+            let search_list = SearchList {
+                domains: domains
+                    .iter()
+                    .map(|domain| DomainName::new(domain))
+                    .collect(),
+                ndots: 1,
+            };
+            Resolver::with_transport(config, transport).with_search_list(search_list)
+        };
+        let www = DomainName::new("www");
+
+        // the nameserver of glueless.com. does not respond
+        let transport = mock_internet().serve(
+            &["199.43.135.53", "2001:500:8f::53"],
+            MockZone::new(
+                "example.com",
+                vec![
+                    record("example.com", soa("example.com")),
+                    record("www.example.com", RData::A([93, 184, 216, 34].into())),
+                    record("ns.example.com", RData::A([192, 0, 2, 53].into())),
+                ],
+            ),
+        );
+        let lookup =
+            resolver(transport, &["glueless.com.", "example.com."]).resolve(&www, QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.example.com"));
+
+        let transport = mock_internet().serve(&["203.0.113.53"], MockZone::failing(5));
+        let lookup =
+            resolver(transport, &["glueless.com.", "example.com."]).resolve(&www, QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.example.com"));
+
+        // NOTIMP
+        let transport = mock_internet().serve(&["203.0.113.53"], MockZone::failing(4));
+        let lookup =
+            resolver(transport, &["glueless.com.", "example.com."]).resolve(&www, QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.example.com"));
+
+        // the only nameserver of glueless.com. refers back to glueless.com. itself
+        let transport = mock_internet().serve(
+            &["203.0.113.53"],
+            MockZone::new(
+                "com",
+                vec![record(
+                    "glueless.com",
+                    RData::NS(DomainName::new("ns.example.com")),
+                )],
+            ),
+        );
+        let lookup =
+            resolver(transport, &["glueless.com.", "example.com."]).resolve(&www, QType::A)?;
+        assert_eq!(lookup.question.qname, DomainName::new("www.example.com"));
+
+        // the failure tells more than www. not existing
+        let transport = mock_internet().serve(&["203.0.113.53"], MockZone::failing(2));
+        let result = resolver(transport, &["glueless.com."]).resolve(&www, QType::A);
+        assert!(
+            matches!(result, Err(Error::ServFail { .. })),
+            "expected SERVFAIL, got {result:?}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_nameserver_rotation() -> Result<()> {
+        let resolver = mock_resolver();
+        // nothing answers at the first address
+        let nameservers = [
+            "192.0.2.53:53".parse().unwrap(),
+            "198.41.0.4:53".parse().unwrap(),
+        ];
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, false);
+        let (resp, nameserver) = resolver.send_query(query, &nameservers)?;
+
+        assert_eq!(nameserver, nameservers[1]);
+        assert!(resp.header.flags.qr());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_answers() -> Result<()> {
+        let resolver = mock_resolver();
+        let www = DomainName::new("www.example.com");
+
+        let lookup = resolver.resolve(&www, QType::A)?;
+        assert!(lookup.nameserver.is_some());
+        let queries_sent = resolver.transport.queries_sent();
+
+        let cached_lookup = resolver.resolve(&www, QType::A)?;
+        assert_eq!(cached_lookup.nameserver, None);
+        assert_eq!(cached_lookup.records.len(), lookup.records.len());
+        assert_eq!(resolver.transport.queries_sent(), queries_sent);
+
+        // the delegation to example.com was cached along the way
+        resolver.resolve(&www, QType::AAAA)?;
+        assert_eq!(resolver.transport.queries_sent(), queries_sent + 1);
+
+        // as was the alias, which leads to the cached answer
+        let alias = resolver.resolve(&DomainName::new("alias.example.com"), QType::A)?;
+        assert_eq!(resolver.transport.queries_sent(), queries_sent + 2);
+        assert_eq!(alias.min_ttl(), 300);
+        let cached_alias = resolver.resolve(&DomainName::new("alias.example.com"), QType::A)?;
+        assert_eq!(
+            cached_alias.cname_chain[0].rdata,
+            alias.cname_chain[0].rdata
+        );
+        assert_eq!(resolver.transport.queries_sent(), queries_sent + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_negative_answers() {
+        let resolver = mock_resolver();
+        let missing = DomainName::new("missing.example.com");
+
+        assert!(matches!(
+            resolver.resolve(&missing, QType::A),
+            Err(Error::NxDomain { .. })
+        ));
+        let queries_sent = resolver.transport.queries_sent();
+
+        // nonexistent names have no records of any type
+        assert!(matches!(
+            resolver.resolve(&missing, QType::MX),
+            Err(Error::NxDomain { .. })
+        ));
+        assert_eq!(resolver.transport.queries_sent(), queries_sent);
+
+        let www = DomainName::new("www.example.com");
+        assert!(matches!(
+            resolver.resolve(&www, QType::MX),
+            Err(Error::NoData { .. })
+        ));
+        assert!(matches!(
+            resolver.resolve(&www, QType::MX),
+            Err(Error::NoData { .. })
+        ));
+        assert_eq!(resolver.transport.queries_sent(), queries_sent + 1);
+    }
+
+    #[test]
+    fn test_nxdomain() {
+        let result = mock_resolver().resolve(&DomainName::new("missing.example.com"), QType::A);
+
+        let Err(Error::NxDomain { name, soa }) = result else {
+            panic!("expected NXDOMAIN, got {result:?}");
+        };
+        assert_eq!(name, DomainName::new("missing.example.com"));
+        assert_eq!(soa.unwrap().name, DomainName::new("example.com"));
+    }
+
+    #[test]
+    fn test_nodata() {
+        let result = mock_resolver().resolve(&DomainName::new("www.example.com"), QType::MX);
+
+        let Err(Error::NoData {
+            name,
+            record_type,
+            soa,
+        }) = result
+        else {
+            panic!("expected NODATA, got {result:?}");
+        };
+        assert_eq!(name, DomainName::new("www.example.com"));
+        assert_eq!(record_type, QType::MX);
+        assert!(soa.is_some());
+    }
+
+    #[test]
+    fn test_nodata_apex() {
+        // the nameservers of example.com. add their own NS records to the negative answer
+        let result = mock_resolver().resolve(&DomainName::new("example.com"), QType::MX);
+
+        let Err(Error::NoData { name, soa, .. }) = result else {
+            panic!("expected NODATA, got {result:?}");
+        };
+        assert_eq!(name, DomainName::new("example.com"));
+        assert_eq!(soa.unwrap().name, DomainName::new("example.com"));
+    }
+
+    #[test]
+    fn test_servfail() {
+        let config = ResolverConfig {
+            retries: 0,
+            ..Default::default()
+        };
+        let transport = MockTransport::default().serve(&["198.41.0.4"], MockZone::failing(2));
+        let hints = RootHints::parse(". NS a.root-servers.net.\na.root-servers.net. A 198.41.0.4");
+        let result = Resolver::with_transport(config, transport)
+            .with_root_hints(hints.unwrap())
+            .resolve(&DomainName::new("www.example.com"), QType::A);
+
+        assert!(matches!(result, Err(Error::ServFail { .. })));
+    }
+
+    #[test]
+    fn test_extended_response_code() {
+        let config = ResolverConfig {
+            retries: 0,
+            ..Default::default()
+        };
+        // BADVERS, which reads as NOERROR in the header alone
+        let transport = MockTransport::default().serve(&["198.41.0.4"], MockZone::failing(16));
+        let hints = RootHints::parse(". NS a.root-servers.net.\na.root-servers.net. A 198.41.0.4");
+        let result = Resolver::with_transport(config, transport)
+            .with_root_hints(hints.unwrap())
+            .resolve(&DomainName::new("www.example.com"), QType::A);
+
+        assert!(
+            matches!(result, Err(Error::ResponseCode { code: 16, .. })),
+            "expected BADVERS, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn test_refused_rotation() -> Result<()> {
+        let transport = mock_internet().serve(&["192.0.2.53"], MockZone::failing(5));
+        let resolver = Resolver::with_transport(ResolverConfig::default(), transport);
+        let nameservers = [
+            "192.0.2.53:53".parse().unwrap(),
+            "198.41.0.4:53".parse().unwrap(),
+        ];
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, false);
+        let (_, nameserver) = resolver.send_query(query, &nameservers)?;
+
+        assert_eq!(nameserver, nameservers[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let config = ResolverConfig {
+            timeout: Duration::from_millis(20),
+            retries: 2,
+            ..Default::default()
+        };
+        let resolver = Resolver::with_transport(config, MockTransport::default());
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, false);
+        let result = resolver.send_query(query, &["192.0.2.53:53".parse().unwrap()]);
+
+        // 20ms, then 40ms, then 80ms
+        assert!(matches!(
+            result,
+            Err(Error::Transport(transport::Error::Timeout { timeout, .. }))
+                if timeout == Duration::from_millis(80)
+        ));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let config = ResolverConfig {
+            timeout: Duration::from_millis(20),
+            retries: 2,
+            deadline: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let resolver = Resolver::with_transport(config, MockTransport::default().waiting());
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, false);
+        let start = Instant::now();
+        let result = resolver.send_query(query, &["192.0.2.53:53".parse().unwrap()]);
+
+        // 20ms, then at most the 30ms left rather than 40ms, and no third attempt
+        assert!(matches!(
+            result,
+            Err(Error::Transport(transport::Error::Timeout { timeout, .. }))
+                if timeout <= Duration::from_millis(30)
+        ));
+        assert!(start.elapsed() < Duration::from_millis(80));
+    }
+
+    #[test]
+    #[ignore = "requires network access"]
+    fn test_resolve() -> Result<()> {
+        let lookup = Resolver::new(ResolverConfig::default())
+            .resolve(&DomainName::new("www.example.com"), QType::A)
+            .expect("Failed to resolve");
+        let correct_ip = "93.184.216.34".parse::<IpAddr>().unwrap();
+        assert_eq!(lookup.ip_addrs().next(), Some(correct_ip));
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires network access"]
+    fn test_cname() -> Result<()> {
+        // facebook has multiple IP addrs, no sense checking for any possible one.
+        let _ = Resolver::new(ResolverConfig::default())
+            .resolve(&DomainName::new("www.facebook.com"), QType::A)
+            .expect("Failed to lookup domain");
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "GitHub CI doesn't support IPv6.\nSee https://github.com/actions/runner-images/issues/668"]
+    fn test_resolve_ipv6() -> Result<()> {
+        let lookup = Resolver::new(ResolverConfig::default())
+            .resolve(&DomainName::new("www.example.com"), QType::AAAA)
+            .expect("Failed to resolve");
+        let correct_ip = "2606:2800:220:1:248:1893:25c8:1946"
+            .parse::<IpAddr>()
+            .unwrap();
+        assert_eq!(lookup.ip_addrs().next(), Some(correct_ip));
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+//! A cache of the RRsets learnt while resolving, kept until their TTL runs out.
+//!
+//! Negative answers are cached as well, for as long as the SOA record of their zone allows,
+//! as described in [RFC 2308 section 5](https://datatracker.ietf.org/doc/html/rfc2308#section-5).
+
+pub mod file;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{dname::DomainName, qclass::QClass, qtype::QType, rdata::RData, record::Record};
+
+/// Identifies an RRset: the records sharing an owner name, type and class
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub name: DomainName,
+    pub qtype: QType,
+    pub class: QClass,
+}
+
+impl CacheKey {
+    pub fn new(name: DomainName, qtype: QType, class: QClass) -> Self {
+        Self { name, qtype, class }
+    }
+
+    /// The key of the RRset the record belongs to
+    pub fn of(record: &Record) -> Self {
+        Self::new(record.name.clone(), record.qtype, record.class)
+    }
+}
+
+/// What the cache knows about an RRset
+///
+/// The TTLs of the returned records only cover the time remaining before they expire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cached {
+    /// The records of the RRset
+    Records(Vec<Record>),
+    /// The owner name does not exist, as stated by the SOA record of its zone
+    NxDomain(Record),
+    /// The owner name has no records of this type, as stated by the SOA record of its zone
+    NoData(Record),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) data: Cached,
+    pub(crate) expires: Instant,
+}
+
+impl Entry {
+    /// Returns the entry's data, with TTLs reduced to the time left before it expires
+    fn aged(&self, now: Instant) -> Option<Cached> {
+        let remaining = self.expires.checked_duration_since(now)?;
+        if remaining.is_zero() {
+            return None;
+        }
+
+        let ttl = remaining.as_secs() as u32;
+        let age = |record: &Record| Record {
+            time_to_live: record.time_to_live.min(ttl),
+            ..record.clone()
+        };
+        Some(match &self.data {
+            Cached::Records(records) => Cached::Records(records.iter().map(age).collect()),
+            Cached::NxDomain(soa) => Cached::NxDomain(age(soa)),
+            Cached::NoData(soa) => Cached::NoData(age(soa)),
+        })
+    }
+}
+
+/// Holds RRsets and negative answers until their TTL expires
+///
+/// Time is passed in explicitly, so entries age the same way whatever clock drives them.
+#[derive(Debug, Clone, Default)]
+pub struct Cache {
+    pub(crate) rrsets: HashMap<CacheKey, Entry>,
+    /// Nonexistent names, which have no records of any type
+    pub(crate) nxdomains: HashMap<(DomainName, QClass), Entry>,
+}
+
+/// The time for which a negative answer may be cached: the lowest of the SOA record's TTL and MINIMUM field
+fn negative_ttl(soa: &Record) -> u32 {
+    match &soa.rdata {
+        RData::SOA(soa_data) => soa.time_to_live.min(soa_data.minimum),
+        _ => 0,
+    }
+}
+
+fn expiry(now: Instant, ttl: u32) -> Instant {
+    now + Duration::from_secs(ttl.into())
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns what is known about the RRset, unless it expired
+    pub fn get(&self, key: &CacheKey, now: Instant) -> Option<Cached> {
+        self.nxdomains
+            .get(&(key.name.clone(), key.class))
+            .and_then(|entry| entry.aged(now))
+            .or_else(|| self.rrsets.get(key).and_then(|entry| entry.aged(now)))
+    }
+
+    /// Returns the records of the RRset, unless it expired or is known not to exist
+    pub fn get_records(&self, key: &CacheKey, now: Instant) -> Option<Vec<Record>> {
+        match self.get(key, now)? {
+            Cached::Records(records) => Some(records),
+            Cached::NxDomain(_) | Cached::NoData(_) => None,
+        }
+    }
+
+    /// Caches the records, grouped into RRsets.
+    ///
+    /// Each RRset expires along with its record of lowest TTL, replacing whatever was cached before.
+    pub fn insert_records(&mut self, records: impl IntoIterator<Item = Record>, now: Instant) {
+        let mut rrsets: HashMap<CacheKey, Vec<Record>> = HashMap::new();
+        for record in records {
+            rrsets
+                .entry(CacheKey::of(&record))
+                .or_default()
+                .push(record);
+        }
+
+        for (key, records) in rrsets {
+            let ttl = records
+                .iter()
+                .map(|rr| rr.time_to_live)
+                .min()
+                .unwrap_or_default();
+            if ttl == 0 {
+                continue;
+            }
+
+            self.nxdomains.remove(&(key.name.clone(), key.class));
+            self.rrsets.insert(
+                key,
+                Entry {
+                    data: Cached::Records(records),
+                    expires: expiry(now, ttl),
+                },
+            );
+        }
+    }
+
+    /// Caches the nonexistence of the name, as stated by the SOA record of its zone
+    pub fn insert_nxdomain(&mut self, name: DomainName, class: QClass, soa: Record, now: Instant) {
+        let ttl = negative_ttl(&soa);
+        if ttl == 0 {
+            return;
+        }
+
+        self.nxdomains.insert(
+            (name, class),
+            Entry {
+                data: Cached::NxDomain(soa),
+                expires: expiry(now, ttl),
+            },
+        );
+    }
+
+    /// Caches the absence of the RRset, as stated by the SOA record of its zone
+    pub fn insert_nodata(&mut self, key: CacheKey, soa: Record, now: Instant) {
+        let ttl = negative_ttl(&soa);
+        if ttl == 0 {
+            return;
+        }
+
+        self.rrsets.insert(
+            key,
+            Entry {
+                data: Cached::NoData(soa),
+                expires: expiry(now, ttl),
+            },
+        );
+    }
+
+    /// Drops every expired entry
+    pub fn remove_expired(&mut self, now: Instant) {
+        self.rrsets.retain(|_, entry| entry.expires > now);
+        self.nxdomains.retain(|_, entry| entry.expires > now);
+    }
+
+    /// The number of entries, including expired ones that were not removed yet
+    pub fn len(&self) -> usize {
+        self.rrsets.len() + self.nxdomains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{convert::fixtures::record, rdata::Soa};
+
+    fn soa_record(ttl: u32, minimum: u32) -> Record {
+        Record {
+            time_to_live: ttl,
+            ..record(
+                "example.com",
+                RData::SOA(Soa {
+                    mname: DomainName::new("ns.example.com"),
+                    rname: DomainName::new("hostmaster.example.com"),
+                    serial: 1,
+                    refresh: 7200,
+                    retry: 3600,
+                    expire: 1209600,
+                    minimum,
+                }),
+            )
+        }
+    }
+
+    #[test]
+    fn cache_rrsets_expire() {
+        let now = Instant::now();
+        let mut cache = Cache::new();
+        cache.insert_records(
+            [
+                record("www.example.com", RData::A([93, 184, 216, 34].into())),
+                Record {
+                    time_to_live: 60,
+                    ..record("www.example.com", RData::A([93, 184, 216, 35].into()))
+                },
+                record("example.com", RData::A([93, 184, 216, 36].into())),
+            ],
+            now,
+        );
+        assert_eq!(cache.len(), 2);
+
+        let key = CacheKey::new(DomainName::new("WWW.example.com"), QType::A, QClass::IN);
+        let records = cache
+            .get_records(&key, now + Duration::from_secs(20))
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|rr| rr.time_to_live == 40));
+
+        assert_eq!(cache.get(&key, now + Duration::from_secs(60)), None);
+        cache.remove_expired(now + Duration::from_secs(60));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn cache_negative_answers() {
+        let now = Instant::now();
+        let mut cache = Cache::new();
+        let missing = DomainName::new("missing.example.com");
+
+        // the SOA record's MINIMUM field caps its TTL
+        cache.insert_nxdomain(missing.clone(), QClass::IN, soa_record(3600, 300), now);
+        let mx_key = CacheKey::new(missing.clone(), QType::MX, QClass::IN);
+        let Some(Cached::NxDomain(soa)) = cache.get(&mx_key, now) else {
+            panic!("expected a cached NXDOMAIN");
+        };
+        assert_eq!(soa.time_to_live, 300);
+        assert_eq!(cache.get(&mx_key, now + Duration::from_secs(300)), None);
+
+        // and the SOA record's TTL caps its MINIMUM field
+        let a_key = CacheKey::new(DomainName::new("example.com"), QType::A, QClass::IN);
+        cache.insert_nodata(a_key.clone(), soa_record(10, 300), now);
+        assert!(matches!(cache.get(&a_key, now), Some(Cached::NoData(_))));
+        assert_eq!(cache.get_records(&a_key, now), None);
+        assert_eq!(cache.get(&a_key, now + Duration::from_secs(10)), None);
+    }
+}
@@ -0,0 +1,340 @@
+//! Saving the cache to a file, so what was learnt outlives a single run.
+//!
+//! Expiry times are stored as wall-clock time, and entries are re-aged against the clock when loaded.
+//! The file is laid out as follows, with every integer in network byte order:
+//!
+//! ```text
+//! +-------+---------+-------+---------+-----+---------+
+//! | MAGIC | VERSION | COUNT | ENTRY 1 | ... | ENTRY n |
+//! +-------+---------+-------+---------+-----+---------+
+//!   "dirt"  u16       u32
+//!
+//! ENTRY:
+//! +------+---------+--------------------------------------------------+
+//! | KIND | EXPIRES | records: COUNT (u16), RECORD...                  |
+//! |  u8  |   u64   | nodata: NAME, TYPE (u16), CLASS (u16), SOA RECORD |
+//! |      |         | nxdomain: NAME, CLASS (u16), SOA RECORD           |
+//! +------+---------+--------------------------------------------------+
+//! ```
+//!
+//! `EXPIRES` counts seconds since the Unix epoch, names and records use the (uncompressed) wire format.
+
+use std::{
+    fs,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use byteorder::{NetworkEndian, ReadBytesExt};
+use thiserror::Error;
+
+use super::{Cache, CacheKey, Cached, Entry};
+use crate::{dname::DomainName, encoder::Encoder, qclass::QClass, qtype::QType, record::Record};
+
+const MAGIC: &[u8; 4] = b"dirt";
+
+const KIND_RECORDS: u8 = 0;
+const KIND_NODATA: u8 = 1;
+const KIND_NXDOMAIN: u8 = 2;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Wraps the errors that may be encountered while saving or loading a cache file
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Stores an error encountered while reading or writing the file
+    #[error("Failed to access the cache file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file does not start with the magic bytes of a cache file
+    #[error("Not a cache file")]
+    Magic,
+    /// The file was written in another version of the format
+    #[error("Unsupported cache file version {0}, expected {ver}", ver = Cache::FILE_VERSION)]
+    Version(u16),
+    /// An entry is neither records, NODATA nor NXDOMAIN
+    #[error("Unknown cache entry kind {0}")]
+    Kind(u8),
+    /// Stores an error encountered while decoding the name of a negative entry
+    #[error("Failed to read a cached name: {0}")]
+    Name(#[from] crate::dname::Error),
+    /// Stores an error encountered while decoding a cached record
+    #[error("Failed to read a cached record: {0}")]
+    Record(#[from] crate::record::Error),
+}
+
+/// The Unix time at which an entry expires, given the current instant and wall-clock time
+fn wall_clock_expiry(expires: Instant, now: Instant, wall_now: SystemTime) -> u64 {
+    let remaining = expires.saturating_duration_since(now);
+    (wall_now + remaining)
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Cache {
+    /// The version of the file format written by [`Cache::save`]
+    pub const FILE_VERSION: u16 = 1;
+
+    /// Where the cache is kept by default: `dirt/cache` under `$XDG_CACHE_HOME`, or else `$HOME/.cache`
+    pub fn default_path() -> Option<PathBuf> {
+        let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+        Some(cache_dir.join("dirt").join("cache"))
+    }
+
+    /// Encodes the entries that did not expire yet, with their expiry as wall-clock time
+    pub fn to_bytes(&self, now: Instant, wall_now: SystemTime) -> Result<Vec<u8>> {
+        let live_rrsets = self.rrsets.iter().filter(|(_, entry)| entry.expires > now);
+        let live_nxdomains = self
+            .nxdomains
+            .iter()
+            .filter(|(_, entry)| entry.expires > now);
+
+        let mut encoder = Encoder::uncompressed();
+        encoder.write_bytes(MAGIC);
+        encoder.write_u16(Self::FILE_VERSION);
+        encoder.write_u32((live_rrsets.clone().count() + live_nxdomains.clone().count()) as u32);
+
+        for (key, entry) in live_rrsets {
+            let expires = wall_clock_expiry(entry.expires, now, wall_now);
+            match &entry.data {
+                Cached::Records(records) => {
+                    encoder.write_u8(KIND_RECORDS);
+                    encoder.write_bytes(&expires.to_be_bytes());
+                    encoder.write_u16(records.len() as u16);
+                    for record in records {
+                        record.encode(&mut encoder)?;
+                    }
+                }
+                Cached::NoData(soa) => {
+                    encoder.write_u8(KIND_NODATA);
+                    encoder.write_bytes(&expires.to_be_bytes());
+                    key.name.encode(&mut encoder);
+                    encoder.write_u16(key.qtype.into());
+                    encoder.write_u16(key.class.into());
+                    soa.encode(&mut encoder)?;
+                }
+                Cached::NxDomain(_) => unreachable!("nonexistent names are cached by name alone"),
+            }
+        }
+
+        for ((name, class), entry) in live_nxdomains {
+            let Cached::NxDomain(soa) = &entry.data else {
+                continue;
+            };
+            encoder.write_u8(KIND_NXDOMAIN);
+            encoder.write_bytes(&wall_clock_expiry(entry.expires, now, wall_now).to_be_bytes());
+            name.encode(&mut encoder);
+            encoder.write_u16((*class).into());
+            soa.encode(&mut encoder)?;
+        }
+
+        Ok(encoder.into_bytes())
+    }
+
+    /// Decodes a cache written by [`Cache::to_bytes`], re-aging its entries against the wall-clock time.
+    ///
+    /// Entries that expired since they were written are dropped.
+    pub fn from_bytes(bytes: &[u8], now: Instant, wall_now: SystemTime) -> Result<Self> {
+        let mut bytes = Cursor::new(bytes);
+
+        let mut magic = [0; MAGIC.len()];
+        bytes.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Magic);
+        }
+        let version = bytes.read_u16::<NetworkEndian>()?;
+        if version != Self::FILE_VERSION {
+            return Err(Error::Version(version));
+        }
+
+        let wall_now = wall_now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut cache = Self::new();
+        for _ in 0..bytes.read_u32::<NetworkEndian>()? {
+            let kind = bytes.read_u8()?;
+            // the entry keeps whatever time it had left, unless it expired while saved
+            let expires = Duration::from_secs(bytes.read_u64::<NetworkEndian>()?)
+                .checked_sub(wall_now)
+                .filter(|remaining| !remaining.is_zero())
+                .map(|remaining| now + remaining);
+
+            match kind {
+                KIND_RECORDS => {
+                    let records = (0..bytes.read_u16::<NetworkEndian>()?)
+                        .map(|_| Record::from_bytes(&mut bytes))
+                        .collect::<crate::record::Result<Vec<Record>>>()?;
+                    if let (Some(key), Some(expires)) = (records.first().map(CacheKey::of), expires)
+                    {
+                        let data = Cached::Records(records);
+                        cache.rrsets.insert(key, Entry { data, expires });
+                    }
+                }
+                KIND_NODATA => {
+                    let name = DomainName::from_bytes(&mut bytes)?;
+                    let qtype = QType::from(bytes.read_u16::<NetworkEndian>()?);
+                    let class = QClass::from(bytes.read_u16::<NetworkEndian>()?);
+                    let data = Cached::NoData(Record::from_bytes(&mut bytes)?);
+                    if let Some(expires) = expires {
+                        let key = CacheKey::new(name, qtype, class);
+                        cache.rrsets.insert(key, Entry { data, expires });
+                    }
+                }
+                KIND_NXDOMAIN => {
+                    let name = DomainName::from_bytes(&mut bytes)?;
+                    let class = QClass::from(bytes.read_u16::<NetworkEndian>()?);
+                    let data = Cached::NxDomain(Record::from_bytes(&mut bytes)?);
+                    if let Some(expires) = expires {
+                        cache
+                            .nxdomains
+                            .insert((name, class), Entry { data, expires });
+                    }
+                }
+                kind => return Err(Error::Kind(kind)),
+            }
+        }
+
+        Ok(cache)
+    }
+
+    /// Reads the cache saved at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        Self::from_bytes(&bytes, Instant::now(), SystemTime::now())
+    }
+
+    /// Saves the cache at `path`, creating its directory if needed.
+    ///
+    /// The cache is written to a temporary file next to `path`, then renamed over it,
+    /// so readers never see a partially written cache.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = self.to_bytes(Instant::now(), SystemTime::now())?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)?;
+
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(format!(".{}.tmp", std::process::id()));
+        let tmp_path = dir.join(tmp_name);
+
+        let written = fs::File::create(&tmp_path).and_then(|mut file| {
+            file.write_all(&bytes)?;
+            file.sync_all()
+        });
+        if let Err(e) = written.and_then(|_| fs::rename(&tmp_path, path)) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        convert::fixtures::record,
+        rdata::{RData, Soa},
+    };
+
+    fn test_cache(now: Instant) -> Cache {
+        let soa = record(
+            "example.com",
+            RData::SOA(Soa {
+                mname: DomainName::new("ns.example.com"),
+                rname: DomainName::new("hostmaster.example.com"),
+                serial: 1,
+                refresh: 7200,
+                retry: 3600,
+                expire: 1209600,
+                minimum: 300,
+            }),
+        );
+
+        let mut cache = Cache::new();
+        cache.insert_records(
+            [
+                record("www.example.com", RData::A([93, 184, 216, 34].into())),
+                record("www.example.com", RData::A([93, 184, 216, 35].into())),
+                Record {
+                    time_to_live: 60,
+                    ..record("example.com", RData::A([93, 184, 216, 36].into()))
+                },
+            ],
+            now,
+        );
+        cache.insert_nodata(
+            CacheKey::new(DomainName::new("www.example.com"), QType::MX, QClass::IN),
+            soa.clone(),
+            now,
+        );
+        cache.insert_nxdomain(DomainName::new("missing.example.com"), QClass::IN, soa, now);
+        cache
+    }
+
+    #[test]
+    fn cache_file_reages_entries() -> Result<()> {
+        let now = Instant::now();
+        let wall_now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let bytes = test_cache(now).to_bytes(now, wall_now)?;
+
+        // loaded 100 seconds of wall-clock time later, by a process with its own clock
+        let later = Instant::now();
+        let cache = Cache::from_bytes(&bytes, later, wall_now + Duration::from_secs(100))?;
+        assert_eq!(cache.len(), 3, "the 60 second RRset expired");
+
+        let www = CacheKey::new(DomainName::new("www.example.com"), QType::A, QClass::IN);
+        let records = cache.get_records(&www, later).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|rr| rr.time_to_live == 3500));
+
+        let mx = CacheKey::new(DomainName::new("www.example.com"), QType::MX, QClass::IN);
+        assert!(matches!(cache.get(&mx, later), Some(Cached::NoData(_))));
+        let missing = CacheKey::new(DomainName::new("missing.example.com"), QType::A, QClass::IN);
+        let Some(Cached::NxDomain(soa)) = cache.get(&missing, later) else {
+            panic!("expected a cached NXDOMAIN");
+        };
+        assert_eq!(soa.time_to_live, 200);
+        Ok(())
+    }
+
+    #[test]
+    fn cache_file_rejects_other_versions() {
+        let now = Instant::now();
+        let mut bytes = test_cache(now).to_bytes(now, SystemTime::now()).unwrap();
+
+        bytes[MAGIC.len()..MAGIC.len() + 2]
+            .copy_from_slice(&(Cache::FILE_VERSION + 1).to_be_bytes());
+        assert!(matches!(
+            Cache::from_bytes(&bytes, now, SystemTime::now()),
+            Err(Error::Version(_))
+        ));
+
+        assert!(matches!(
+            Cache::from_bytes(b"not a cache", now, SystemTime::now()),
+            Err(Error::Magic)
+        ));
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(Cache::from_bytes(truncated, now, SystemTime::now()).is_err());
+    }
+
+    #[test]
+    fn cache_file_save_load() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("dirt-cache-test-{}", std::process::id()));
+        let path = dir.join("nested").join("cache");
+
+        test_cache(Instant::now()).save(&path)?;
+        Cache::new().save(&path)?;
+        let loaded = Cache::load(&path);
+
+        // only the renamed file is left behind
+        let files = fs::read_dir(path.parent().unwrap())?.count();
+        fs::remove_dir_all(&dir)?;
+        assert!(loaded?.is_empty());
+        assert_eq!(files, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! The names and addresses of the root nameservers, from which every resolution starts.
+//!
+//! Hints are read from files in the format of the `named.root` file published by IANA,
+//! a subset of the master file format of [RFC 1035 section 5](https://datatracker.ietf.org/doc/html/rfc1035#section-5).
+//! They only need to list a few reachable roots: the full set is learnt from them by a priming query,
+//! as described in [RFC 8109](https://datatracker.ietf.org/doc/html/rfc8109).
+
+use std::{net::IpAddr, path::Path};
+
+use thiserror::Error;
+
+use crate::{dname::DomainName, qclass::QClass, qtype::QType, rdata::RData, record::Record};
+
+/// The `named.root` file published by IANA, listing all 13 roots
+const NAMED_ROOT: &str = include_str!("named.root");
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Wraps the errors that may be encountered while reading root hints
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Stores an error encountered while reading the hints file
+    #[error("Failed to read the root hints: {0}")]
+    Io(#[from] std::io::Error),
+    /// A line does not hold a valid NS, A or AAAA record, or an NS record of a zone other than the root
+    #[error("Line {line} of the root hints is invalid: {reason}")]
+    Invalid { line: usize, reason: String },
+    /// The hints hold no NS records of the root zone
+    #[error("The root hints list no root nameservers")]
+    NoNameservers,
+}
+
+/// The root nameservers, along with their addresses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootHints {
+    /// The NS records of the root zone
+    pub ns_records: Vec<Record>,
+    /// The A and AAAA records of the root nameservers
+    pub addr_records: Vec<Record>,
+}
+
+impl Default for RootHints {
+    /// The hints built from IANA's `named.root` file
+    fn default() -> Self {
+        Self::parse(NAMED_ROOT).expect("the built-in root hints are valid")
+    }
+}
+
+impl RootHints {
+    /// Parses hints in the format of `named.root`.
+    ///
+    /// Each line holds a record as `NAME [TTL] [CLASS] TYPE DATA`, where the type is NS, A or AAAA.
+    /// Everything following a `;` is a comment.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut hints = Self {
+            ns_records: vec![],
+            addr_records: vec![],
+        };
+
+        for (idx, line) in text.lines().enumerate() {
+            let invalid = |reason: String| Error::Invalid {
+                line: idx + 1,
+                reason,
+            };
+
+            let line = line.split(';').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let Some(name) = fields.next() else {
+                continue;
+            };
+            let name: DomainName = name.parse().map_err(|e| invalid(format!("{e}")))?;
+
+            let mut field = fields.next();
+            let time_to_live = match field.map(str::parse::<u32>) {
+                Some(Ok(ttl)) => {
+                    field = fields.next();
+                    ttl
+                }
+                _ => 0,
+            };
+            if field.is_some_and(|class| class.eq_ignore_ascii_case("IN")) {
+                field = fields.next();
+            }
+
+            let data = fields.next();
+            let (qtype, rdata) = match (field.map(str::to_ascii_uppercase).as_deref(), data) {
+                (Some("NS"), Some(ns_name)) => (
+                    QType::NS,
+                    RData::NS(ns_name.parse().map_err(|e| invalid(format!("{e}")))?),
+                ),
+                (Some("A"), Some(addr)) => (
+                    QType::A,
+                    RData::A(addr.parse().map_err(|e| invalid(format!("{e}")))?),
+                ),
+                (Some("AAAA"), Some(addr)) => (
+                    QType::AAAA,
+                    RData::AAAA(addr.parse().map_err(|e| invalid(format!("{e}")))?),
+                ),
+                (Some(record_type), Some(_)) => {
+                    return Err(invalid(format!("unexpected {record_type} record")))
+                }
+                _ => return Err(invalid("missing record type or data".to_string())),
+            };
+            if fields.next().is_some() {
+                return Err(invalid("unexpected data after the record".to_string()));
+            }
+
+            let record = Record {
+                name,
+                qtype,
+                class: QClass::IN,
+                time_to_live,
+                rdata,
+            };
+            match record.qtype {
+                QType::NS if record.name.is_root() => hints.ns_records.push(record),
+                QType::NS => return Err(invalid(format!("NS record of \"{}\"", record.name))),
+                _ => hints.addr_records.push(record),
+            }
+        }
+
+        if hints.ns_records.is_empty() {
+            return Err(Error::NoNameservers);
+        }
+        Ok(hints)
+    }
+
+    /// Reads hints from a file in the format of `named.root`
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// The addresses of the root nameservers, in the order they were listed
+    pub fn addrs(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.addr_records
+            .iter()
+            .filter(|rr| {
+                self.ns_records
+                    .iter()
+                    .any(|ns| ns.rdata.as_domain_name() == Some(&rr.name))
+            })
+            .filter_map(|rr| rr.rdata.as_ip_addr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_root_hints() {
+        let hints = RootHints::default();
+
+        assert_eq!(hints.ns_records.len(), 13);
+        assert_eq!(hints.addrs().filter(IpAddr::is_ipv4).count(), 13);
+        assert_eq!(hints.addrs().filter(IpAddr::is_ipv6).count(), 13);
+        assert_eq!(
+            hints.addrs().next(),
+            Some(IpAddr::from([198, 41, 0, 4])),
+            "a.root-servers.net comes first"
+        );
+    }
+
+    #[test]
+    fn parse_root_hints() -> Result<()> {
+        let hints = RootHints::parse(
+            "; a comment\n\
+             \n\
+             .  3600000 IN NS a.root-servers.net. ; trailing comment\n\
+             a.root-servers.net.  A  198.41.0.4\n\
+             unused.example.  3600000  AAAA  2001:db8::1\n",
+        )?;
+        assert_eq!(hints.ns_records[0].time_to_live, 3600000);
+        assert_eq!(hints.addr_records.len(), 2);
+        // only the addresses of root nameservers are used
+        assert_eq!(
+            hints.addrs().collect::<Vec<_>>(),
+            [IpAddr::from([198, 41, 0, 4])]
+        );
+
+        for (text, line) in [
+            (
+                ". NS a.root-servers.net.\na.root-servers.net. A 198.41.0",
+                2,
+            ),
+            (". NS a.root-servers.net.\na.root-servers.net. MX mail", 2),
+            ("com. NS a.gtld-servers.net.", 1),
+            (". NS", 1),
+        ] {
+            assert!(
+                matches!(RootHints::parse(text), Err(Error::Invalid { line: l, .. }) if l == line),
+                "accepted {text:?}"
+            );
+        }
+        assert!(matches!(
+            RootHints::parse("a.root-servers.net. A 198.41.0.4"),
+            Err(Error::NoNameservers)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+//! An in-memory stand-in for the nameservers of the Internet, serving canned zones.
+
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    io::Cursor,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use super::transport::{self, validate_response, Transport, DNS_PORT};
+use crate::{
+    convert::fixtures::record,
+    dname::DomainName,
+    edns::Edns,
+    message::Message,
+    qtype::QType,
+    rdata::{RData, Soa},
+    record::Record,
+};
+
+/// A nameserver serving a single zone from canned records
+#[derive(Clone)]
+pub(crate) struct MockZone {
+    origin: DomainName,
+    records: Vec<Record>,
+    /// The 12-bit response code of every response, if the nameserver always fails
+    failure: Option<u16>,
+    /// Records slipped into every answer and referral, as a malicious nameserver would
+    forged: Vec<Record>,
+    /// Whether the nameserver only answers queries desiring recursion, as an upstream recursive nameserver
+    recursive: bool,
+}
+
+impl MockZone {
+    pub(crate) fn new(origin: &str, records: Vec<Record>) -> Self {
+        Self {
+            origin: DomainName::new(origin),
+            records,
+            failure: None,
+            forged: vec![],
+            recursive: false,
+        }
+    }
+
+    /// A recursive nameserver answering from the records, refusing queries that do not desire recursion
+    pub(crate) fn recursive(records: Vec<Record>) -> Self {
+        Self {
+            recursive: true,
+            ..Self::new(".", records)
+        }
+    }
+
+    /// A nameserver answering every query with the given response code,
+    /// whose upper bits are carried by EDNS(0) when it does not fit in the header
+    pub(crate) fn failing(response_code: u16) -> Self {
+        Self {
+            failure: Some(response_code),
+            ..Self::new(".", vec![])
+        }
+    }
+
+    fn records_at<'a>(
+        &'a self,
+        name: &'a DomainName,
+        qtype: QType,
+    ) -> impl Iterator<Item = Record> + 'a {
+        self.records
+            .iter()
+            .filter(move |rr| rr.name == *name && rr.qtype == qtype)
+            .cloned()
+    }
+
+    /// Answers the query from the zone's records, referring to a delegated zone if needed
+    fn answer(&self, query: &Message) -> Message {
+        let question = query.get_query();
+        let mut resp = query.clone();
+        resp.header.flags.set_qr(true);
+
+        if let Some(response_code) = self.failure {
+            let [upper, lower] = (response_code << 4).to_be_bytes();
+            resp.header.flags.set_response_code(lower >> 4).unwrap();
+            if upper != 0 {
+                resp.edns = Some(Edns {
+                    extended_rcode: upper,
+                    ..Edns::default()
+                });
+            }
+            return resp;
+        }
+        if self.recursive {
+            if !query.header.flags.recursion_desired() {
+                resp.header.flags.set_response_code(5).unwrap();
+                return resp;
+            }
+            resp.header.flags.set_recursion_avail(true);
+        }
+
+        // follow aliases within the zone, as far as they lead
+        let mut name = question.qname.clone();
+        loop {
+            let records: Vec<Record> = self.records_at(&name, question.qtype).collect();
+            if !records.is_empty() {
+                // along with the addresses of nameservers, as in priming responses
+                for ns_dname in records.iter().filter_map(|rr| match &rr.rdata {
+                    RData::NS(ns_dname) => Some(ns_dname),
+                    _ => None,
+                }) {
+                    resp.additionals.extend(self.records_at(ns_dname, QType::A));
+                    resp.additionals
+                        .extend(self.records_at(ns_dname, QType::AAAA));
+                }
+                resp.answers.extend(records);
+                break;
+            }
+
+            let cname_rr = self.records_at(&name, QType::CNAME).next();
+            match cname_rr {
+                Some(cname_rr) if !resp.answers.contains(&cname_rr) => {
+                    name = cname_rr.rdata.as_domain_name().unwrap().clone();
+                    resp.answers.push(cname_rr);
+                }
+                _ => break,
+            }
+        }
+        if !resp.answers.is_empty() {
+            resp.header.flags.set_authoritative(true);
+            resp.answers.extend(self.forged.iter().cloned());
+            return resp;
+        }
+
+        // refer to the closest zone delegated below the origin
+        let delegation = std::iter::successors(Some(question.qname.clone()), |name| name.parent())
+            .take_while(|name| *name != self.origin)
+            .map(|name| self.records_at(&name, QType::NS).collect::<Vec<_>>())
+            .find(|ns_records| !ns_records.is_empty());
+
+        if let Some(ns_records) = delegation {
+            for ns in &ns_records {
+                let ns_dname = ns.rdata.as_domain_name().unwrap();
+                resp.additionals.extend(self.records_at(ns_dname, QType::A));
+                resp.additionals
+                    .extend(self.records_at(ns_dname, QType::AAAA));
+            }
+            resp.authorities = ns_records;
+            for forged in &self.forged {
+                match forged.qtype {
+                    QType::NS => resp.authorities.push(forged.clone()),
+                    _ => resp.additionals.push(forged.clone()),
+                }
+            }
+            return resp;
+        }
+
+        // the name has no data of the requested type, or does not exist at all
+        resp.header.flags.set_authoritative(true);
+        if !self.records.iter().any(|rr| rr.name == question.qname) {
+            resp.header.flags.set_response_code(3).unwrap();
+        } else {
+            // some nameservers list themselves along with the absence of data
+            resp.authorities
+                .extend(self.records_at(&self.origin, QType::NS));
+        }
+        // along with the SOA record of the closest enclosing zone
+        resp.authorities.extend(
+            std::iter::successors(Some(question.qname.clone()), |name| name.parent())
+                .find_map(|name| self.records_at(&name, QType::SOA).next()),
+        );
+        resp
+    }
+}
+
+/// The data of the SOA record of a zone, allowing negative answers to be cached for 5 minutes
+pub(crate) fn soa(origin: &str) -> RData {
+    RData::SOA(Soa {
+        mname: DomainName::new(&format!("ns.{origin}")),
+        rname: DomainName::new(&format!("hostmaster.{origin}")),
+        serial: 1,
+        refresh: 7200,
+        retry: 3600,
+        expire: 1209600,
+        minimum: 300,
+    })
+}
+
+/// Serves canned zones from in-memory nameservers.
+///
+/// Every message goes through its wire format, as it would over the network.
+#[derive(Default)]
+pub(crate) struct MockTransport {
+    servers: HashMap<SocketAddr, MockZone>,
+    queries_sent: Cell<usize>,
+    /// Whether queries to unknown addresses wait for their timeout, rather than failing at once
+    waiting: bool,
+}
+
+impl MockTransport {
+    pub(crate) fn serve(mut self, ips: &[&str], zone: MockZone) -> Self {
+        for ip in ips {
+            let ip: IpAddr = ip.parse().unwrap();
+            self.servers
+                .insert(SocketAddr::from((ip, DNS_PORT)), zone.clone());
+        }
+        self
+    }
+
+    /// Makes the nameservers at the addresses add the records to the answer section of every answer,
+    /// and to every referral, NS records to the authority section and others to the additional one
+    pub(crate) fn forging(mut self, ips: &[&str], records: Vec<Record>) -> Self {
+        for ip in ips {
+            let ip: IpAddr = ip.parse().unwrap();
+            if let Some(zone) = self.servers.get_mut(&SocketAddr::from((ip, DNS_PORT))) {
+                zone.forged = records.clone();
+            }
+        }
+        self
+    }
+
+    /// Makes queries to unknown addresses wait for their whole timeout, as unreachable nameservers would
+    pub(crate) fn waiting(mut self) -> Self {
+        self.waiting = true;
+        self
+    }
+
+    /// The number of queries received by any nameserver
+    pub(crate) fn queries_sent(&self) -> usize {
+        self.queries_sent.get()
+    }
+}
+
+impl Transport for MockTransport {
+    fn query(
+        &self,
+        query: Message,
+        nameserver: SocketAddr,
+        timeout: Duration,
+    ) -> transport::Result<Message> {
+        let Some(zone) = self.servers.get(&nameserver) else {
+            if self.waiting {
+                std::thread::sleep(timeout);
+            }
+            return Err(transport::Error::Timeout {
+                server: nameserver,
+                timeout,
+            });
+        };
+        self.queries_sent.set(self.queries_sent.get() + 1);
+
+        let query_bytes = query.clone().query_into_bytes()?;
+        let received_query = Message::from_bytes(&mut Cursor::new(query_bytes.as_slice()))?;
+
+        let resp_bytes = zone.answer(&received_query).into_bytes()?;
+        let resp = Message::from_bytes(&mut Cursor::new(resp_bytes.as_slice()))?;
+
+        validate_response(&query, &resp)?;
+        Ok(resp)
+    }
+}
+
+/// The root, `com.`, `net.` and `example.com.` zones, along with a `glueless.com.` zone
+/// whose nameserver is only found by resolving its name.
+///
+/// The nameserver of `example.com.` lies in `net.`, so its glue from `com.` is out of bailiwick.
+/// `loop.example.com.` and `loop.glueless.com.` are aliases of each other,
+/// and the nameserver of `selfish.com.` lies within it without glue.
+pub(crate) fn mock_internet() -> MockTransport {
+    MockTransport::default()
+        .serve(
+            &["198.41.0.4", "2001:503:ba3e::2:30"],
+            MockZone::new(
+                ".",
+                vec![
+                    record(".", RData::NS(DomainName::new("a.root-servers.net"))),
+                    record("a.root-servers.net", RData::A([198, 41, 0, 4].into())),
+                    record(
+                        "a.root-servers.net",
+                        RData::AAAA("2001:503:ba3e::2:30".parse().unwrap()),
+                    ),
+                    record("com", RData::NS(DomainName::new("a.gtld-servers.net"))),
+                    record("net", RData::NS(DomainName::new("b.gtld-servers.net"))),
+                    record("a.gtld-servers.net", RData::A([192, 5, 6, 30].into())),
+                    record(
+                        "a.gtld-servers.net",
+                        RData::AAAA("2001:503:a83e::2:30".parse().unwrap()),
+                    ),
+                    record("b.gtld-servers.net", RData::A([192, 33, 14, 30].into())),
+                    record(
+                        "b.gtld-servers.net",
+                        RData::AAAA("2001:503:231d::2:30".parse().unwrap()),
+                    ),
+                ],
+            ),
+        )
+        .serve(
+            &["192.33.14.30", "2001:503:231d::2:30"],
+            MockZone::new(
+                "net",
+                vec![
+                    record("net", soa("net")),
+                    record("a.iana-servers.net", RData::A([199, 43, 135, 53].into())),
+                    record(
+                        "a.iana-servers.net",
+                        RData::AAAA("2001:500:8f::53".parse().unwrap()),
+                    ),
+                ],
+            ),
+        )
+        .serve(
+            &["192.5.6.30", "2001:503:a83e::2:30"],
+            MockZone::new(
+                "com",
+                vec![
+                    record("com", soa("com")),
+                    record(
+                        "example.com",
+                        RData::NS(DomainName::new("a.iana-servers.net")),
+                    ),
+                    record("a.iana-servers.net", RData::A([199, 43, 135, 53].into())),
+                    record(
+                        "a.iana-servers.net",
+                        RData::AAAA("2001:500:8f::53".parse().unwrap()),
+                    ),
+                    record("glueless.com", RData::NS(DomainName::new("ns.example.com"))),
+                    // its nameserver can only be found through itself
+                    record("selfish.com", RData::NS(DomainName::new("ns.selfish.com"))),
+                ],
+            ),
+        )
+        .serve(
+            &["199.43.135.53", "2001:500:8f::53"],
+            MockZone::new(
+                "example.com",
+                vec![
+                    record("example.com", soa("example.com")),
+                    record(
+                        "example.com",
+                        RData::NS(DomainName::new("a.iana-servers.net")),
+                    ),
+                    record("www.example.com", RData::A([93, 184, 216, 34].into())),
+                    record(
+                        "www.example.com",
+                        RData::AAAA("2606:2800:220:1:248:1893:25c8:1946".parse().unwrap()),
+                    ),
+                    record("www.example.com", RData::A([93, 184, 216, 35].into())),
+                    Record {
+                        time_to_live: 300,
+                        ..record(
+                            "alias.example.com",
+                            RData::CNAME(DomainName::new("www.example.com")),
+                        )
+                    },
+                    record(
+                        "cdn.example.com",
+                        RData::CNAME(DomainName::new("www.glueless.com")),
+                    ),
+                    record("ns.example.com", RData::A([203, 0, 113, 53].into())),
+                    record(
+                        "loop.example.com",
+                        RData::CNAME(DomainName::new("loop.glueless.com")),
+                    ),
+                ],
+            ),
+        )
+        .serve(
+            &["203.0.113.53"],
+            MockZone::new(
+                "glueless.com",
+                vec![
+                    record("glueless.com", soa("glueless.com")),
+                    record("www.glueless.com", RData::A([192, 0, 2, 1].into())),
+                    record(
+                        "loop.glueless.com",
+                        RData::CNAME(DomainName::new("loop.example.com")),
+                    ),
+                ],
+            ),
+        )
+}
@@ -0,0 +1,296 @@
+//! The stub resolver configuration of the system, as read from `/etc/resolv.conf`.
+//!
+//! See more in [resolv.conf(5)](https://man7.org/linux/man-pages/man5/resolv.conf.5.html)
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
+
+use super::{ResolverConfig, DNS_PORT};
+use crate::dname::DomainName;
+
+/// The settings of `resolv.conf` that affect how names are resolved
+///
+/// Settings left out of the file keep the defaults of the C library's resolver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// The recursive nameservers to send queries to, in order
+    pub nameservers: Vec<SocketAddr>,
+    /// The domains appended to relative names, in order
+    pub search: Vec<DomainName>,
+    /// The number of dots from which a relative name is first tried as is, before the search list
+    pub ndots: u8,
+    /// How long to wait for a response from a nameserver
+    pub timeout: Duration,
+    /// How many rounds of queries are sent over the nameservers before giving up
+    pub attempts: u32,
+}
+
+/// Turns relative names into the absolute names to try in turn, as the C library's resolver does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchList {
+    /// The domains appended to relative names, in order
+    pub domains: Vec<DomainName>,
+    /// The number of dots from which a relative name is first tried as is, before the search list
+    pub ndots: u8,
+}
+
+impl Default for SearchList {
+    fn default() -> Self {
+        Self {
+            domains: vec![],
+            ndots: 1,
+        }
+    }
+}
+
+impl SearchList {
+    /// The absolute names to try for `name`, in order.
+    ///
+    /// Absolute names are only tried as is.
+    /// Relative names with at least `ndots` dots are tried as is first, then with each search domain appended,
+    /// and others with each search domain appended first, then as is.
+    pub fn candidates(&self, name: &DomainName) -> Vec<DomainName> {
+        if name.is_absolute() {
+            return vec![name.clone()];
+        }
+
+        let as_is = name.clone().into_absolute();
+        // names that would grow too long are left out
+        let expanded = self
+            .domains
+            .iter()
+            .filter_map(|domain| name.append(&domain.clone().into_absolute()).ok());
+
+        let dots = name.num_labels().saturating_sub(1);
+        match dots >= self.ndots.into() {
+            true => std::iter::once(as_is).chain(expanded).collect(),
+            false => expanded.chain(std::iter::once(as_is)).collect(),
+        }
+    }
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        Self {
+            nameservers: vec![],
+            search: vec![],
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+}
+
+impl ResolvConf {
+    /// Where the system's configuration lives
+    pub const DEFAULT_PATH: &'static str = "/etc/resolv.conf";
+    /// The most nameservers that are used, as with the C library
+    pub const MAX_NAMESERVERS: usize = 3;
+
+    /// Parses the contents of a `resolv.conf` file.
+    ///
+    /// As with the C library, lines that cannot be understood are skipped,
+    /// and the last `search` or `domain` line wins.
+    pub fn parse(text: &str) -> Self {
+        let mut conf = Self::default();
+
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(keyword) = fields.next() else {
+                continue;
+            };
+
+            match keyword {
+                _ if keyword.starts_with(['#', ';']) => {}
+                "nameserver" => {
+                    // link-local IPv6 addresses may carry a zone, which is not supported
+                    let addr = fields.next().and_then(|addr| addr.parse::<IpAddr>().ok());
+                    match addr {
+                        Some(_) if conf.nameservers.len() == Self::MAX_NAMESERVERS => {
+                            tracing::warn!(
+                                "Ignoring nameservers past the first {}: \"{line}\"",
+                                Self::MAX_NAMESERVERS
+                            )
+                        }
+                        Some(ip) => conf.nameservers.push(SocketAddr::from((ip, DNS_PORT))),
+                        None => tracing::warn!("Ignoring invalid nameserver: \"{line}\""),
+                    }
+                }
+                "domain" | "search" => {
+                    conf.search = fields
+                        .filter_map(|domain| {
+                            domain
+                                .parse::<DomainName>()
+                                .inspect_err(|e| {
+                                    tracing::warn!(
+                                        "Ignoring invalid search domain \"{domain}\": {e}"
+                                    )
+                                })
+                                .ok()
+                        })
+                        .map(DomainName::into_absolute)
+                        .collect();
+                    if keyword == "domain" {
+                        conf.search.truncate(1);
+                    }
+                }
+                "options" => {
+                    for option in fields {
+                        conf.set_option(option);
+                    }
+                }
+                _ => tracing::debug!("Ignoring unsupported resolv.conf line: \"{line}\""),
+            }
+        }
+
+        conf
+    }
+
+    /// Applies an option of an `options` line, capping its value as the C library does
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = option.split_once(':').unwrap_or((option, ""));
+        let value = value.parse::<u32>().ok();
+        match (name, value) {
+            ("ndots", Some(ndots)) => self.ndots = ndots.min(15) as u8,
+            ("timeout", Some(secs)) => self.timeout = Duration::from_secs(secs.clamp(1, 30).into()),
+            ("attempts", Some(attempts)) => self.attempts = attempts.clamp(1, 5),
+            _ => tracing::debug!("Ignoring unsupported resolv.conf option \"{option}\""),
+        }
+    }
+
+    /// Reads the configuration at `path`
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// The nameservers to query, falling back to one on the local host if none are configured
+    pub fn nameservers(&self) -> Vec<SocketAddr> {
+        match self.nameservers.is_empty() {
+            true => vec![SocketAddr::from(([127, 0, 0, 1], DNS_PORT))],
+            false => self.nameservers.clone(),
+        }
+    }
+
+    /// The search domains and ndots option of the configuration
+    pub fn search_list(&self) -> SearchList {
+        SearchList {
+            domains: self.search.clone(),
+            ndots: self.ndots,
+        }
+    }
+
+    /// The timeout and retries of the configuration, on top of `config`
+    pub fn resolver_config(&self, config: ResolverConfig) -> ResolverConfig {
+        ResolverConfig {
+            timeout: self.timeout,
+            retries: self.attempts - 1,
+            ..config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resolv_conf() {
+        let conf = ResolvConf::parse(
+            "# generated by NetworkManager\n\
+             domain corp.example\n\
+             search corp.example lab.corp.example\n\
+             nameserver 10.0.0.53\n\
+             nameserver fd00::53\n\
+             nameserver not-an-address\n\
+             ; another comment\n\
+             options ndots:2 timeout:3 attempts:9 rotate\n\
+             sortlist 130.155.160.0/255.255.240.0\n",
+        );
+
+        assert_eq!(
+            conf.nameservers,
+            [
+                "10.0.0.53:53".parse().unwrap(),
+                "[fd00::53]:53".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            conf.search,
+            [
+                DomainName::new("corp.example."),
+                DomainName::new("lab.corp.example.")
+            ]
+        );
+        assert!(conf.search[0].is_absolute());
+        assert_eq!(conf.ndots, 2);
+        assert_eq!(conf.timeout, Duration::from_secs(3));
+        assert_eq!(conf.attempts, 5, "attempts are capped");
+
+        let config = conf.resolver_config(ResolverConfig::default());
+        assert_eq!(config.timeout, Duration::from_secs(3));
+        assert_eq!(config.retries, 4);
+    }
+
+    #[test]
+    fn search_list_candidates() {
+        let search_list = SearchList {
+            domains: vec![
+                DomainName::new("corp.example."),
+                DomainName::new("lab.corp.example."),
+            ],
+            ndots: 2,
+        };
+        let candidates = |name| -> Vec<String> {
+            search_list
+                .candidates(&DomainName::new(name))
+                .iter()
+                .map(DomainName::to_string)
+                .collect()
+        };
+
+        assert_eq!(
+            candidates("build01"),
+            [
+                "build01.corp.example.",
+                "build01.lab.corp.example.",
+                "build01."
+            ]
+        );
+        // fewer dots than ndots
+        assert_eq!(
+            candidates("build01.lab"),
+            [
+                "build01.lab.corp.example.",
+                "build01.lab.lab.corp.example.",
+                "build01.lab."
+            ]
+        );
+        assert_eq!(
+            candidates("www.example.com"),
+            [
+                "www.example.com.",
+                "www.example.com.corp.example.",
+                "www.example.com.lab.corp.example."
+            ]
+        );
+        assert_eq!(candidates("build01."), ["build01."]);
+        assert_eq!(
+            SearchList::default().candidates(&DomainName::new("build01")),
+            [DomainName::new("build01.")]
+        );
+    }
+
+    #[test]
+    fn parse_resolv_conf_defaults() {
+        // the last of domain and search wins
+        let conf = ResolvConf::parse("search a.example b.example\ndomain c.example\n");
+
+        assert_eq!(conf.search, [DomainName::new("c.example")]);
+        assert_eq!(conf.ndots, 1);
+        assert_eq!(conf.nameservers(), ["127.0.0.1:53".parse().unwrap()]);
+        assert_eq!(ResolvConf::parse(""), ResolvConf::default());
+    }
+}
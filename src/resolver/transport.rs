@@ -0,0 +1,503 @@
+//! Exchanging messages with nameservers.
+//!
+//! Queries are sent over UDP, and retried over TCP when the response is truncated.
+//! Over TCP, messages are prefixed with a two byte length field.
+//!
+//! See more in [RFC 1035 section 4.2](https://datatracker.ietf.org/doc/html/rfc1035#section-4.2)
+//! and [RFC 7766](https://datatracker.ietf.org/doc/html/rfc7766)
+
+use std::{
+    io::{Cursor, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    edns::Edns,
+    header::{Header, ResponseCode},
+    message::Message,
+    question::Question,
+};
+
+/// The port name servers listen on, over both UDP and TCP
+pub const DNS_PORT: u16 = 53;
+
+/// The size of the buffer UDP responses are received into, advertised to servers through EDNS(0)
+const UDP_RECV_BUF_SIZE: u16 = Edns::DEFAULT_UDP_PAYLOAD_SIZE;
+
+/// Exchanges messages with nameservers
+pub trait Transport {
+    /// Sends the query to the nameserver at the given address, and returns its response.
+    ///
+    /// Fails if no valid response arrives within the timeout.
+    fn query(&self, query: Message, nameserver: SocketAddr, timeout: Duration) -> Result<Message>;
+}
+
+/// Sends queries over the network, using UDP and falling back to TCP for truncated responses
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetworkTransport;
+
+impl Transport for NetworkTransport {
+    fn query(&self, query: Message, nameserver: SocketAddr, timeout: Duration) -> Result<Message> {
+        send_query_to(query, nameserver, timeout)
+    }
+}
+
+/// Returns a ready-to-use UDP socket connected to the given address
+#[tracing::instrument]
+pub fn setup_udp_socket_to(dns_server_addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    let udp_sock = match dns_server_addr {
+        SocketAddr::V4(_) => UdpSocket::bind((std::net::Ipv4Addr::UNSPECIFIED, 0))?,
+        SocketAddr::V6(_) => UdpSocket::bind((std::net::Ipv6Addr::UNSPECIFIED, 0))?,
+    };
+
+    tracing::trace!(
+        "Successfully bound to local addr: {}",
+        udp_sock.local_addr().unwrap()
+    );
+
+    udp_sock.connect(dns_server_addr)?;
+    tracing::trace!("Successfully connected to remote addr: {dns_server_addr}");
+
+    Ok(udp_sock)
+}
+
+/// Returns a TCP stream connected to the given address
+#[tracing::instrument]
+pub fn setup_tcp_stream_to(
+    dns_server_addr: SocketAddr,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    let tcp_stream = TcpStream::connect_timeout(&dns_server_addr, timeout)?;
+    tracing::trace!(
+        "Successfully connected to remote addr: {dns_server_addr} from local addr: {}",
+        tcp_stream.local_addr().unwrap()
+    );
+
+    Ok(tcp_stream)
+}
+
+/// Sends the query over UDP, retrying over TCP if the response was truncated,
+/// and without EDNS(0) if the server does not support it
+fn send_query_to(
+    mut query: Message,
+    socket_addr: SocketAddr,
+    timeout: Duration,
+) -> Result<Message> {
+    // without EDNS(0), servers limit UDP responses to 512 bytes
+    query
+        .edns
+        .get_or_insert_with(|| Edns::new(UDP_RECV_BUF_SIZE));
+
+    tracing::trace!("Sending query for {:?}", query.get_query());
+
+    let resp = exchange(&query, socket_addr, timeout)?;
+    // servers that do not implement EDNS(0) reject the OPT record without one of their own (RFC 6891 section 7)
+    if resp.header.flags.response_code() == ResponseCode::FormErr && resp.edns.is_none() {
+        tracing::debug!("{socket_addr} rejected EDNS(0), retrying without it");
+        query.edns = None;
+        return exchange(&query, socket_addr, timeout);
+    }
+    Ok(resp)
+}
+
+/// Sends the query over UDP, retrying over TCP if the response was truncated
+fn exchange(query: &Message, socket_addr: SocketAddr, timeout: Duration) -> Result<Message> {
+    if let Some(resp) = send_udp_query(query, socket_addr, timeout)? {
+        return Ok(resp);
+    }
+
+    tracing::debug!("Response from {socket_addr} was truncated, retrying over TCP");
+    send_tcp_query(query, socket_addr, timeout)
+}
+
+/// Checks that the response answers the given query
+pub fn validate_response(query: &Message, resp: &Message) -> Result<()> {
+    if resp.header.id != query.header.id {
+        return Err(Error::IdMismatch {
+            expected: query.header.id,
+            received: resp.header.id,
+        });
+    }
+
+    if !resp.header.flags.qr() {
+        return Err(Error::NotResponse);
+    }
+
+    if resp.questions.as_slice() != std::slice::from_ref(query.get_query()) {
+        return Err(Error::QuestionMismatch {
+            expected: query.get_query().clone(),
+            received: resp.questions.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Sends the query over UDP, returning the first valid response received before the deadline,
+/// or `None` if the response was truncated.
+///
+/// Datagrams that do not come from the server or do not answer the query are discarded.
+/// Only the header of a truncated response is read, as the rest may be cut anywhere.
+fn send_udp_query(
+    query: &Message,
+    socket_addr: SocketAddr,
+    timeout: Duration,
+) -> Result<Option<Message>> {
+    // connection setup
+    let udp_sock = setup_udp_socket_to(socket_addr)?;
+
+    // query request
+    tracing::trace!("Sending query via {udp_sock:?}");
+    udp_sock.send(&query.clone().query_into_bytes()?)?;
+    tracing::trace!("Query sent successfully");
+
+    // get response
+    let deadline = Instant::now() + timeout;
+    let mut recv_buf = [0u8; UDP_RECV_BUF_SIZE as usize];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Timeout {
+                server: socket_addr,
+                timeout,
+            });
+        }
+        udp_sock.set_read_timeout(Some(remaining))?;
+
+        let (bytes_recv, src_addr) = match udp_sock.recv_from(&mut recv_buf) {
+            Ok(recv) => recv,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(Error::Timeout {
+                    server: socket_addr,
+                    timeout,
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+        tracing::trace!("Received {bytes_recv} bytes from {src_addr}");
+
+        if src_addr != socket_addr {
+            tracing::warn!("Discarding datagram from unexpected source {src_addr}");
+            continue;
+        }
+
+        let datagram = &recv_buf[..bytes_recv];
+        let header = match Header::from_bytes(&mut Cursor::new(datagram)) {
+            Ok(header) => header,
+            Err(e) => {
+                tracing::warn!("Discarding malformed response: {e}");
+                continue;
+            }
+        };
+        let answers_query = header.id == query.header.id && header.flags.qr();
+        if answers_query && header.flags.truncated() {
+            return Ok(None);
+        }
+
+        // parse response to message
+        let resp = match Message::from_bytes(&mut Cursor::new(datagram)) {
+            Ok(resp) => resp,
+            // a response larger than the buffer was cut to fit it
+            Err(e) if answers_query && bytes_recv == recv_buf.len() => {
+                tracing::debug!("Response from {socket_addr} overflowed the buffer: {e}");
+                return Ok(None);
+            }
+            Err(e) => {
+                tracing::warn!("Discarding malformed response: {e}");
+                continue;
+            }
+        };
+
+        match validate_response(query, &resp) {
+            Ok(()) => return Ok(Some(resp)),
+            Err(e) => tracing::warn!("Discarding mismatched response: {e}"),
+        }
+    }
+}
+
+/// Sends the query over TCP, as described in RFC 1035 section 4.2.2.
+///
+/// Messages sent over TCP are prefixed with a two byte length field.
+/// See more in [RFC 7766](https://datatracker.ietf.org/doc/html/rfc7766)
+fn send_tcp_query(query: &Message, socket_addr: SocketAddr, timeout: Duration) -> Result<Message> {
+    // connection setup
+    let mut tcp_stream = setup_tcp_stream_to(socket_addr, timeout)?;
+    tcp_stream.set_read_timeout(Some(timeout))?;
+
+    // query request, with its length prefix in the same write (RFC 7766 section 8)
+    let query_bytes = query.clone().query_into_bytes()?;
+    let query_len = u16::try_from(query_bytes.len()).map_err(std::io::Error::other)?;
+    let mut framed_query = Vec::with_capacity(2 + query_bytes.len());
+    framed_query.extend_from_slice(&query_len.to_be_bytes());
+    framed_query.extend_from_slice(&query_bytes);
+
+    tracing::trace!("Sending query via {tcp_stream:?}");
+    tcp_stream.write_all(&framed_query)?;
+    tracing::trace!("Query sent successfully");
+
+    // get response, which may be split across any number of segments
+    let mut len_buf = [0u8; 2];
+    tcp_stream.read_exact(&mut len_buf)?;
+    let mut recv_buf = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+    tcp_stream.read_exact(&mut recv_buf)?;
+    tracing::trace!("Received {} bytes in response", recv_buf.len());
+
+    // parse response to message
+    let mut msg_bytes_reader = Cursor::new(recv_buf.as_slice());
+    let resp = Message::from_bytes(&mut msg_bytes_reader)?;
+
+    // the connection is only used for this query, so any mismatch is an error
+    validate_response(query, &resp)?;
+    Ok(resp)
+}
+
+/// Wraps the errors that may be encountered while sending a query and receiving its response
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Stores an error encountered while using [std::io] traits and structs
+    #[error("Failed to exchange messages: {0}")]
+    Io(#[from] std::io::Error),
+    /// Encountered while decoding a response
+    #[error(transparent)]
+    Message(#[from] crate::message::Error),
+    /// The response's ID differs from the query's
+    #[error("Response ID {received:#06x} does not match query ID {expected:#06x}")]
+    IdMismatch { expected: u16, received: u16 },
+    /// The response does not have its QR bit set
+    #[error("Received a query instead of a response")]
+    NotResponse,
+    /// The response's question section differs from the query's
+    #[error("Response questions {received:?} do not match query question {expected:?}")]
+    QuestionMismatch {
+        expected: Question,
+        received: Vec<Question>,
+    },
+    /// No valid response arrived before the deadline
+    #[error("No valid response from {server} within {timeout:?}")]
+    Timeout {
+        server: SocketAddr,
+        timeout: Duration,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+
+    use crate::{dname::DomainName, qclass::QClass, qtype::QType, rdata::RData, record::Record};
+
+    /// Builds a response to the given query bytes, answering it unless it is truncated
+    fn answer_query(query_bytes: &[u8], truncated: bool) -> Vec<u8> {
+        let mut resp = Message::from_bytes(&mut Cursor::new(query_bytes)).unwrap();
+        resp.header.flags.set_qr(true).set_truncated(truncated);
+        if !truncated {
+            resp.answers.push(Record {
+                name: resp.get_query().qname.clone(),
+                qtype: QType::A,
+                class: QClass::IN,
+                time_to_live: 3600,
+                rdata: RData::A([93, 184, 216, 34].into()),
+            });
+        }
+        resp.into_bytes().unwrap()
+    }
+
+    /// Answers a single query over TCP, writing the response a few bytes at a time
+    fn serve_tcp_query(listener: TcpListener) -> std::io::Result<()> {
+        let (mut stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+
+        let mut len_buf = [0u8; 2];
+        stream.read_exact(&mut len_buf)?;
+        let mut query_buf = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+        stream.read_exact(&mut query_buf)?;
+
+        let resp = answer_query(&query_buf, false);
+        let mut framed_resp = (resp.len() as u16).to_be_bytes().to_vec();
+        framed_resp.extend(resp);
+        for chunk in framed_resp.chunks(3) {
+            stream.write_all(chunk)?;
+            stream.flush()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tcp_partial_reads() -> Result<()> {
+        let listener = TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+        let server_addr = listener.local_addr()?;
+        let server = std::thread::spawn(move || serve_tcp_query(listener));
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp = send_tcp_query(&query, server_addr, Duration::from_secs(5))?;
+        server.join().unwrap()?;
+
+        assert!(resp.header.flags.qr());
+        assert_eq!(
+            resp.answers[0].rdata.as_ip_addr(),
+            Some([93, 184, 216, 34].into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncation_fallback() -> Result<()> {
+        let udp_sock = UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+        let server_addr = udp_sock.local_addr()?;
+        let listener = TcpListener::bind(server_addr)?;
+        let server = std::thread::spawn(move || -> std::io::Result<()> {
+            let mut recv_buf = [0u8; 512];
+            let (bytes_recv, client_addr) = udp_sock.recv_from(&mut recv_buf)?;
+            udp_sock.send_to(&answer_query(&recv_buf[..bytes_recv], true), client_addr)?;
+            serve_tcp_query(listener)
+        });
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp = send_query_to(query, server_addr, Duration::from_secs(5))?;
+        server.join().unwrap()?;
+
+        assert!(!resp.header.flags.truncated());
+        assert_eq!(resp.answers.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncation_mid_record() -> Result<()> {
+        let udp_sock = UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+        let server_addr = udp_sock.local_addr()?;
+        let listener = TcpListener::bind(server_addr)?;
+        let server = std::thread::spawn(move || -> std::io::Result<()> {
+            let mut recv_buf = [0u8; 512];
+            let (bytes_recv, client_addr) = udp_sock.recv_from(&mut recv_buf)?;
+
+            // the answer is cut in the middle of its address, with the TC bit set
+            let mut resp = answer_query(&recv_buf[..bytes_recv], false);
+            resp[2] |= 0b10;
+            let edns_len = 11;
+            resp.truncate(resp.len() - edns_len - 2);
+            udp_sock.send_to(&resp, client_addr)?;
+            serve_tcp_query(listener)
+        });
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp = send_query_to(query, server_addr, Duration::from_secs(5))?;
+        server.join().unwrap()?;
+
+        assert!(!resp.header.flags.truncated());
+        assert_eq!(resp.answers.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_edns_fallback() -> Result<()> {
+        let udp_sock = UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+        let server_addr = udp_sock.local_addr()?;
+        let server = std::thread::spawn(move || -> std::io::Result<Vec<bool>> {
+            let mut recv_buf = [0u8; 512];
+            let mut carried_opt = vec![];
+            for _ in 0..2 {
+                let (bytes_recv, client_addr) = udp_sock.recv_from(&mut recv_buf)?;
+                let query = Message::from_bytes(&mut Cursor::new(&recv_buf[..bytes_recv])).unwrap();
+                carried_opt.push(query.edns.is_some());
+
+                // a server predating EDNS(0) does not understand the OPT record
+                let resp = match query.edns {
+                    Some(_) => {
+                        let mut resp = query;
+                        resp.header.flags.set_qr(true).set_response_code(1).unwrap();
+                        resp.edns = None;
+                        resp.into_bytes().unwrap()
+                    }
+                    None => answer_query(&recv_buf[..bytes_recv], false),
+                };
+                udp_sock.send_to(&resp, client_addr)?;
+            }
+            Ok(carried_opt)
+        });
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp = send_query_to(query, server_addr, Duration::from_secs(5))?;
+
+        assert_eq!(server.join().unwrap()?, [true, false]);
+        assert_eq!(resp.header.flags.response_code(), ResponseCode::NoError);
+        assert_eq!(resp.answers.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_response() {
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp_bytes = answer_query(&query.clone().query_into_bytes().unwrap(), false);
+        let resp = Message::from_bytes(&mut Cursor::new(resp_bytes.as_slice())).unwrap();
+        assert!(validate_response(&query, &resp).is_ok());
+
+        let mut wrong_id = resp.clone();
+        wrong_id.header.id = query.header.id.wrapping_add(1);
+        assert!(matches!(
+            validate_response(&query, &wrong_id),
+            Err(Error::IdMismatch { .. })
+        ));
+
+        let mut not_resp = resp.clone();
+        not_resp.header.flags.set_qr(false);
+        assert!(matches!(
+            validate_response(&query, &not_resp),
+            Err(Error::NotResponse)
+        ));
+
+        let mut wrong_question = resp;
+        wrong_question.questions[0].qname = DomainName::new("example.com");
+        assert!(matches!(
+            validate_response(&query, &wrong_question),
+            Err(Error::QuestionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_udp_discards_mismatched() -> Result<()> {
+        let udp_sock = UdpSocket::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+        let server_addr = udp_sock.local_addr()?;
+        let server = std::thread::spawn(move || -> std::io::Result<()> {
+            let mut recv_buf = [0u8; 512];
+            let (bytes_recv, client_addr) = udp_sock.recv_from(&mut recv_buf)?;
+            let resp = answer_query(&recv_buf[..bytes_recv], false);
+
+            // a spoofed response with the wrong ID, then garbage, then the real response
+            let mut spoofed = resp.clone();
+            spoofed[0] ^= 0xFF;
+            udp_sock.send_to(&spoofed, client_addr)?;
+            udp_sock.send_to(&[0xDE, 0xAD], client_addr)?;
+            udp_sock.send_to(&resp, client_addr)?;
+            Ok(())
+        });
+
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let resp = send_udp_query(&query, server_addr, Duration::from_secs(5))?.unwrap();
+        server.join().unwrap()?;
+
+        assert_eq!(resp.header.id, query.header.id);
+        assert_eq!(resp.answers.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires network access"]
+    fn test_send_query() -> std::io::Result<()> {
+        let query = Message::new_query(DomainName::new("www.example.com"), QType::A, false, true);
+        let query_bytes = query.query_into_bytes().unwrap();
+
+        // connection setup
+        let udp_sock =
+            setup_udp_socket_to("8.8.8.8:53".parse().unwrap()).expect("Failed to setup UDP socket");
+
+        // query request
+        udp_sock.send(&query_bytes).expect("Couldn't send query");
+
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
 pub mod dname;
+pub mod edns;
+pub mod encoder;
 pub mod header;
 pub mod message;
+pub mod message_ref;
 pub mod qclass;
 pub mod qtype;
 #[deprecated]
 pub mod query;
 pub mod question;
+pub mod rdata;
 pub mod record;
@@ -3,20 +3,75 @@
 //! See more in [RFC 1034](https://datatracker.ietf.org/doc/html/rfc1034)
 //! and [RFC 1035 section 3.1](https://datatracker.ietf.org/doc/html/rfc1035#section-3.1)
 
-pub(crate) mod label {
+pub mod label {
 
     use thiserror::Error;
 
     /// Labels are the individual nodes or components of a [`DomainName`]
-    #[derive(Debug, Clone, PartialEq, Eq)]
-    pub(crate) struct Label(pub(crate) String);
+    ///
+    /// A label may hold any octets, including dots and non-printable bytes.
+    ///
+    /// Labels are compared without regard to the case of ASCII letters, but keep their original case.
+    #[derive(Debug, Clone)]
+    pub struct Label(pub(crate) Vec<u8>);
 
     impl Label {
         /// The maximum size of a single label within a domain name
         pub const MAX_LABEL_SIZE: usize = 63;
 
-        pub fn new(string: String) -> Self {
-            Self(string)
+        pub fn new(bytes: Vec<u8>) -> Self {
+            Self(bytes)
+        }
+
+        /// The label's octets, in their original case
+        pub fn as_bytes(&self) -> &[u8] {
+            &self.0
+        }
+
+        /// The number of octets in the label
+        pub fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        /// Whether the label has no octets.
+        ///
+        /// Only the root's null label is empty, so an empty label cannot be part of a domain name.
+        pub fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        /// The label's octets, with uppercase ASCII letters set to lowercase
+        fn lowercase_bytes(&self) -> impl Iterator<Item = u8> + '_ {
+            self.0.iter().map(u8::to_ascii_lowercase)
+        }
+    }
+
+    impl PartialEq for Label {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.eq_ignore_ascii_case(&other.0)
+        }
+    }
+
+    impl Eq for Label {}
+
+    impl std::hash::Hash for Label {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            state.write_usize(self.0.len());
+            self.lowercase_bytes().for_each(|byte| state.write_u8(byte));
+        }
+    }
+
+    impl PartialOrd for Label {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Label {
+        /// Compares labels as left-justified, lowercased octet sequences,
+        /// where the absence of an octet sorts before a zero octet.
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.lowercase_bytes().cmp(other.lowercase_bytes())
         }
     }
 
@@ -30,24 +85,59 @@ pub(crate) mod label {
             dest_amt: usize,
             source: std::io::Error,
         },
-        /// Stores an error encountered while converting from a sequence of [u8] to [String]
-        #[error("Failed to convert byte slice {bytes:?} to string slice:\n\t{source}")]
-        Convert {
-            bytes: Vec<u8>,
-            source: std::str::Utf8Error,
-        },
     }
 
     pub(crate) type Result<T> = std::result::Result<T, Error>;
 }
 
-pub(crate) use label::Label;
+pub use label::Label;
 
 use thiserror::Error;
 
 /// Domain names define a name of a node in requests and responses
-#[derive(Clone, PartialEq, Eq)]
-pub struct DomainName(pub(crate) Vec<Label>);
+///
+/// A name is either absolute (fully qualified, ending at the root)
+/// or relative (e.g. "www", to be completed by a search list).
+/// Names read from messages are always absolute; in presentation form, absolute names end with a dot.
+///
+/// Names are compared and hashed without regard to the case of ASCII letters,
+/// as required by [RFC 4343](https://datatracker.ietf.org/doc/html/rfc4343),
+/// while keeping their original case for display.
+/// Only the labels are compared, so an absolute name equals the relative name with the same labels.
+#[derive(Clone)]
+pub struct DomainName {
+    pub(crate) labels: Vec<Label>,
+    pub(crate) absolute: bool,
+}
+
+impl PartialEq for DomainName {
+    fn eq(&self, other: &Self) -> bool {
+        self.labels == other.labels
+    }
+}
+
+impl Eq for DomainName {}
+
+impl std::hash::Hash for DomainName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.labels.hash(state);
+    }
+}
+
+impl PartialOrd for DomainName {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DomainName {
+    /// Orders names canonically, as described in [RFC 4034 section 6.1](https://datatracker.ietf.org/doc/html/rfc4034#section-6.1):
+    /// names are sorted by their most significant (rightmost) labels first,
+    /// and a name sorts before the names below it.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.labels.iter().rev().cmp(other.labels.iter().rev())
+    }
+}
 
 impl DomainName {
     /// The maximum number of octets that represent a domain name (i.e., the sum of all label octets and label lengths)
@@ -55,19 +145,37 @@ impl DomainName {
     pub const MAX_UDP_MSG_SIZE: usize = 512;
     /// a domain name is terminated by a length byte of zero
     pub const TERMINATOR: u8 = 0;
+    /// the maximum number of compression pointers followed while reading a single name
+    pub const MAX_POINTER_HOPS: usize = 127;
+    /// the two high bits set in the first octet of a compression pointer
+    pub const POINTER_MASK: u16 = 0b1100_0000_0000_0000;
 }
 
 impl std::fmt::Debug for DomainName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("DomainName")
-            .field(&String::from(self.clone()))
+            .field(&self.to_string())
             .finish()
     }
 }
 
 impl std::fmt::Display for DomainName {
+    /// Writes the name in its presentation form, escaping special characters within labels
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &String::from(self.clone()))
+        if self.is_root() {
+            return f.write_str(".");
+        }
+
+        for (idx, label) in self.labels.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{label}")?;
+        }
+        if self.absolute {
+            f.write_str(".")?;
+        }
+        Ok(())
     }
 }
 
@@ -76,9 +184,131 @@ impl DomainName {
         size & 0b1100_0000 == 0b1100_0000
     }
 
-    /// Creates a new [`DomainName`]
+    /// Creates a new [`DomainName`] from its presentation form
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name is not valid, see [`DomainName::from_str`](std::str::FromStr) for a fallible alternative.
     pub fn new(domain_name: &str) -> Self {
-        Self::from(domain_name.to_string())
+        domain_name
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid domain name \"{domain_name}\": {e}"))
+    }
+
+    /// Creates the root [`DomainName`], which has no labels
+    pub fn root() -> Self {
+        Self {
+            labels: vec![],
+            absolute: true,
+        }
+    }
+
+    /// Whether this is the root
+    pub fn is_root(&self) -> bool {
+        self.absolute && self.labels.is_empty()
+    }
+
+    /// Whether this name is fully qualified, i.e. ends at the root
+    pub fn is_absolute(&self) -> bool {
+        self.absolute
+    }
+
+    /// Returns this name as a fully qualified name
+    pub fn into_absolute(mut self) -> Self {
+        self.absolute = true;
+        self
+    }
+
+    /// Iterates over the labels of this name, from the leftmost (least significant) one
+    pub fn labels(&self) -> impl DoubleEndedIterator<Item = &Label> + ExactSizeIterator {
+        self.labels.iter()
+    }
+
+    /// The number of labels in this name, not counting the root's null label
+    pub fn num_labels(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// The number of octets this name takes up in a message when not compressed
+    pub fn wire_len(&self) -> usize {
+        self.labels
+            .iter()
+            .map(|label| 1 + label.len())
+            .sum::<usize>()
+            + 1
+    }
+
+    /// Returns the name one level up in the hierarchy, i.e. without its leftmost label
+    ///
+    /// The root, and relative names with a single label, have no parent.
+    pub fn parent(&self) -> Option<Self> {
+        if self.labels.is_empty() || (!self.absolute && self.labels.len() == 1) {
+            return None;
+        }
+
+        Some(Self {
+            labels: self.labels[1..].to_vec(),
+            absolute: self.absolute,
+        })
+    }
+
+    /// Whether this name is equal to, or below, the given name
+    ///
+    /// Every name is a subdomain of the root.
+    pub fn is_subdomain_of(&self, other: &DomainName) -> bool {
+        self.labels.len() >= other.labels.len()
+            && self
+                .labels
+                .iter()
+                .rev()
+                .zip(other.labels.iter().rev())
+                .all(|(label, other_label)| label == other_label)
+    }
+
+    /// Returns a new name with `suffix` appended to this relative name
+    ///
+    /// The new name is absolute if `suffix` is.
+    pub fn append(&self, suffix: &DomainName) -> Result<Self> {
+        if self.absolute {
+            return Err(Error::Absolute(self.clone()));
+        }
+
+        Self::checked(
+            self.labels.iter().chain(&suffix.labels).cloned().collect(),
+            suffix.absolute,
+        )
+    }
+
+    /// Returns a new name with `label` added in front of this name
+    pub fn prepend(&self, label: Label) -> Result<Self> {
+        Self::checked(
+            std::iter::once(label)
+                .chain(self.labels.iter().cloned())
+                .collect(),
+            self.absolute,
+        )
+    }
+
+    /// Creates a [`DomainName`], checking for empty labels and the label and name size limits
+    pub(crate) fn checked(labels: Vec<Label>, absolute: bool) -> Result<Self> {
+        if labels.iter().any(Label::is_empty) {
+            return Err(Error::EmptyLabel(Self { labels, absolute }.to_string()));
+        }
+        if let Some(label) = labels
+            .iter()
+            .find(|label| label.len() > Label::MAX_LABEL_SIZE)
+        {
+            return Err(Error::LabelTooLong { size: label.len() });
+        }
+
+        let name = Self { labels, absolute };
+        if name.wire_len() > Self::MAX_NAME_SIZE {
+            return Err(Error::NameTooLong {
+                size: name.wire_len(),
+            });
+        }
+
+        Ok(name)
     }
 }
 
@@ -97,4 +327,31 @@ pub enum Error {
     /// Stores an error encountered while using [std::io] traits and structs
     #[error("Failed to parse domain name data:\n\t{0}")]
     Io(#[from] std::io::Error),
+    /// A compression pointer did not point strictly before the labels containing it
+    #[error("Compression pointer at offset {position} points forward to offset {pointer}")]
+    ForwardPointer { pointer: u16, position: u64 },
+    /// More compression pointers were followed than a valid name could need
+    #[error("Followed {hops} compression pointers while reading a single name")]
+    PointerLimit { hops: usize },
+    /// A label length exceeded the maximum label size (or used a reserved label type)
+    #[error(
+        "Label length {size} exceeds the maximum of {} octets",
+        Label::MAX_LABEL_SIZE
+    )]
+    LabelTooLong { size: usize },
+    /// The name's labels and length octets exceeded the maximum name size
+    #[error(
+        "Name of {size} octets exceeds the maximum of {} octets",
+        DomainName::MAX_NAME_SIZE
+    )]
+    NameTooLong { size: usize },
+    /// Attempted to extend a name that already ends at the root
+    #[error("Cannot append to the absolute name \"{0}\"")]
+    Absolute(DomainName),
+    /// A name contained an empty label besides the root's, e.g. "example..com"
+    #[error("Domain name \"{0}\" contains an empty label")]
+    EmptyLabel(String),
+    /// A name in presentation form contained an incomplete or out of range escape sequence
+    #[error("Domain name \"{0}\" contains an invalid escape sequence")]
+    InvalidEscape(String),
 }
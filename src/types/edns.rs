@@ -0,0 +1,151 @@
+//! Extension Mechanisms for DNS (EDNS(0)) let requesters and responders advertise
+//! their capabilities through an OPT pseudo-record in the additional section.
+//!
+//! The OPT record reuses the fixed record fields as follows:
+//!
+//! ```text
+//! +------------+--------------+------------------------------+
+//! | Field Name | Field Type   | Description                  |
+//! +------------+--------------+------------------------------+
+//! | NAME       | domain name  | MUST be 0 (root domain)      |
+//! | TYPE       | u_int16_t    | OPT (41)                     |
+//! | CLASS      | u_int16_t    | requester's UDP payload size |
+//! | TTL        | u_int32_t    | extended RCODE and flags     |
+//! | RDLEN      | u_int16_t    | length of all RDATA          |
+//! | RDATA      | octet stream | {attribute,value} pairs      |
+//! +------------+--------------+------------------------------+
+//!
+//!             +0 (MSB)                            +1 (LSB)
+//!  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+//!  |         EXTENDED-RCODE        |            VERSION            |
+//!  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+//!  | DO|                           Z                               |
+//!  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+//! ```
+//!
+//! See more in [RFC 6891 section 6](https://datatracker.ietf.org/doc/html/rfc6891#section-6)
+
+/// The EDNS(0) information carried by a message's OPT pseudo-record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// The number of octets of the largest UDP payload that can be reassembled and delivered in the sender's network stack.
+    pub udp_payload_size: u16,
+    /// The upper 8 bits of the extended 12-bit RCODE. The lower 4 bits are the header's RCODE.
+    pub extended_rcode: u8,
+    /// The version of the implementation. Only version 0 is defined.
+    pub version: u8,
+    /// DNSSEC OK - indicates that the requester is able to accept DNSSEC security RRs
+    pub dnssec_ok: bool,
+    /// The options carried in the pseudo-record's data
+    pub options: Vec<EdnsOption>,
+}
+
+impl Edns {
+    /// A payload size that avoids IP fragmentation on virtually all paths (see DNS Flag Day 2020)
+    pub const DEFAULT_UDP_PAYLOAD_SIZE: u16 = 1232;
+    /// The bit of the flags field that holds [`Edns::dnssec_ok`]
+    pub const DO_MASK: u16 = 0b1000_0000_0000_0000;
+
+    /// Creates a new version 0 [`Edns`] advertising the given UDP payload size
+    pub fn new(udp_payload_size: u16) -> Self {
+        Self {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            options: vec![],
+        }
+    }
+}
+
+impl Default for Edns {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_UDP_PAYLOAD_SIZE)
+    }
+}
+
+/// A single {attribute, value} pair of an OPT pseudo-record
+///
+/// ```text
+///             +0 (MSB)                            +1 (LSB)
+///  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  |                          OPTION-CODE                          |
+///  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  |                         OPTION-LENGTH                         |
+///  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+///  /                          OPTION-DATA                          /
+///  +---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+---+
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EdnsOption {
+    /// Name server identifier (see RFC 5001). Empty in queries.
+    Nsid(Vec<u8>),
+    /// The network the query originated from (see RFC 7871)
+    ClientSubnet {
+        /// The address family, as assigned by IANA (1 for IPv4, 2 for IPv6)
+        family: u16,
+        /// The number of leftmost significant bits of the address
+        source_prefix: u8,
+        /// The number of leftmost bits of the address the response covers
+        scope_prefix: u8,
+        /// The significant octets of the address
+        address: Vec<u8>,
+    },
+    /// A DNS cookie (see RFC 7873)
+    Cookie {
+        /// The requester's cookie
+        client: [u8; 8],
+        /// The responder's cookie, empty if not known yet
+        server: Vec<u8>,
+    },
+    /// The idle timeout of a TCP connection, in units of 100 milliseconds (see RFC 7828). Absent in queries.
+    TcpKeepalive(Option<u16>),
+    /// The number of zero octets used to pad the message (see RFC 7830)
+    Padding(u16),
+    /// An option without a dedicated representation
+    Unknown {
+        /// The option's code
+        code: u16,
+        /// The option's raw data
+        data: Vec<u8>,
+    },
+}
+
+impl EdnsOption {
+    pub const NSID: u16 = 3;
+    pub const CLIENT_SUBNET: u16 = 8;
+    pub const COOKIE: u16 = 10;
+    pub const TCP_KEEPALIVE: u16 = 11;
+    pub const PADDING: u16 = 12;
+
+    /// The code identifying this option
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Nsid(_) => Self::NSID,
+            EdnsOption::ClientSubnet { .. } => Self::CLIENT_SUBNET,
+            EdnsOption::Cookie { .. } => Self::COOKIE,
+            EdnsOption::TcpKeepalive(_) => Self::TCP_KEEPALIVE,
+            EdnsOption::Padding(_) => Self::PADDING,
+            EdnsOption::Unknown { code, .. } => *code,
+        }
+    }
+}
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+/// Wraps the errors that may be encountered during decoding of an [`Edns`]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Stores an error encountered while using [std::io] traits and structs
+    #[error("Failed to parse EDNS data: {0}")]
+    Io(#[from] std::io::Error),
+    /// The OPT record was not owned by the root domain
+    #[error("OPT record must be owned by the root domain, found \"{0}\"")]
+    Owner(crate::dname::DomainName),
+    /// The data of an option did not match its declared length
+    #[error("EDNS option {code} has invalid length {length}")]
+    OptionLength { code: u16, length: u16 },
+    /// A message carried more than one OPT record
+    #[error("Message contains more than one OPT record")]
+    Duplicate,
+}
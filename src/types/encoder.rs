@@ -0,0 +1,87 @@
+//! Messages are encoded into a single buffer, which lets domain names refer back to
+//! previously written names instead of repeating their labels.
+//!
+//! See more in [RFC 1035 section 4.1.4](https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.4)
+
+use std::collections::HashMap;
+
+/// An encoding context for the wire format of a message
+///
+/// Remembers the offset of every domain name suffix written so far,
+/// so later occurrences of the same suffix can be replaced by a compression pointer.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+    /// Offsets of previously written name suffixes, keyed by their lowercased labels
+    names: HashMap<Vec<Vec<u8>>, u16>,
+    compress: bool,
+}
+
+impl Encoder {
+    /// The largest offset a compression pointer can refer to (14 bits)
+    pub const MAX_POINTER_OFFSET: usize = 0x3FFF;
+
+    /// Creates a new [`Encoder`] that compresses domain names
+    pub fn new() -> Self {
+        Self {
+            compress: true,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a new [`Encoder`] that always writes domain names in full
+    pub fn uncompressed() -> Self {
+        Self::default()
+    }
+
+    /// Whether names written to this encoder may be compressed
+    pub fn compresses(&self) -> bool {
+        self.compress
+    }
+
+    /// The current length of the encoded data, i.e. the offset of the next written byte
+    pub fn position(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn write_u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn write_u16(&mut self, val: u16) {
+        self.buf.extend(val.to_be_bytes());
+    }
+
+    pub fn write_u32(&mut self, val: u32) {
+        self.buf.extend(val.to_be_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Overwrites the two bytes at `pos` with `val`, e.g. to fill in a length once it is known
+    pub fn set_u16_at(&mut self, pos: usize, val: u16) {
+        self.buf[pos..pos + 2].copy_from_slice(&val.to_be_bytes());
+    }
+
+    /// Returns the offset of a previously written name suffix, if there is one
+    pub(crate) fn find_name(&self, suffix: &[Vec<u8>]) -> Option<u16> {
+        if !self.compress {
+            return None;
+        }
+        self.names.get(suffix).copied()
+    }
+
+    /// Remembers that the name suffix was written at `pos`
+    pub(crate) fn remember_name(&mut self, suffix: Vec<Vec<u8>>, pos: usize) {
+        if self.compress && pos <= Self::MAX_POINTER_OFFSET {
+            self.names.entry(suffix).or_insert(pos as u16);
+        }
+    }
+
+    /// Consumes the encoder, returning the encoded bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
@@ -141,9 +141,8 @@ impl std::fmt::Display for OpCode {
 }
 
 /// This 4 bit field is set as part of responses.
-#[derive(Debug, Clone, Copy, num_enum::IntoPrimitive, PartialEq, Eq, Default)]
-#[repr(u8)]
-pub(crate) enum ResponseCode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseCode {
     /// No error condition
     #[default]
     NoError,
@@ -164,7 +163,23 @@ pub(crate) enum ResponseCode {
     /// a particular operation (e.g., zone transfer) for particular data.
     Refused,
     /// Reserved for future use. (6-15)
-    Reserved,
+    ///
+    /// Holds the code, which later RFCs assigned and which may be extended by EDNS(0).
+    Reserved(u8),
+}
+
+impl From<ResponseCode> for u8 {
+    fn from(value: ResponseCode) -> Self {
+        match value {
+            ResponseCode::NoError => 0,
+            ResponseCode::FormErr => 1,
+            ResponseCode::ServFail => 2,
+            ResponseCode::NxDomain => 3,
+            ResponseCode::NotImp => 4,
+            ResponseCode::Refused => 5,
+            ResponseCode::Reserved(code) => code,
+        }
+    }
 }
 
 impl TryFrom<u8> for ResponseCode {
@@ -178,7 +193,7 @@ impl TryFrom<u8> for ResponseCode {
             3 => Ok(Self::NxDomain),
             4 => Ok(Self::NotImp),
             5 => Ok(Self::Refused),
-            6..=15 => Ok(Self::Reserved),
+            6..=15 => Ok(Self::Reserved(value)),
             invalid => Err(Error::ResponseCode(invalid)),
         }
     }
@@ -193,7 +208,7 @@ impl std::fmt::Display for ResponseCode {
             ResponseCode::NxDomain => f.write_str("Nonexistent Domain"),
             ResponseCode::NotImp => f.write_str("Not Implemented"),
             ResponseCode::Refused => f.write_str("Refused"),
-            ResponseCode::Reserved => f.write_str("Reserved"),
+            ResponseCode::Reserved(code) => write!(f, "Reserved ({code})"),
         }
     }
 }
@@ -235,6 +250,39 @@ pub struct HeaderFlags {
     pub(crate) response_code: ResponseCode,
 }
 
+// getters
+impl HeaderFlags {
+    /// Whether this message is a response
+    pub fn qr(&self) -> bool {
+        self.query_response
+    }
+
+    /// Whether the responding name server is an authority for the domain name in question
+    pub fn authoritative(&self) -> bool {
+        self.auth_answer
+    }
+
+    /// Whether this message was truncated by the transmission channel
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Whether recursion was requested
+    pub fn recursion_desired(&self) -> bool {
+        self.recursion_desired
+    }
+
+    /// Whether recursive query support is available in the responding name server
+    pub fn recursion_avail(&self) -> bool {
+        self.recursion_avail
+    }
+
+    /// The outcome of the query this message responds to
+    pub fn response_code(&self) -> ResponseCode {
+        self.response_code
+    }
+}
+
 // non-consuming builders
 impl HeaderFlags {
     /// Set the QR bit for this header
@@ -1,6 +1,6 @@
 use crate::{
-    dname::DomainName, header::Header, qclass::QClass, qtype::QType, question::Question,
-    record::Record,
+    dname::DomainName, edns::Edns, header::Header, qclass::QClass, qtype::QType,
+    question::Question, record::Record,
 };
 
 /// All communications inside of the domain protocol are carried in a single format called a message.
@@ -20,7 +20,7 @@ use crate::{
 /// |      Additional     | RRs holding additional information
 /// +---------------------+
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Message {
     pub header: Header,
     /// The query name(s) and other query parameters.
@@ -32,7 +32,11 @@ pub struct Message {
     /// May optionally carry the SOA RR for the authoritative data in the answer section
     pub authorities: Vec<Record>,
     /// RRs which may be helpful in using the RRs in the other sections.
+    ///
+    /// Does not include the OPT pseudo-record, which is held in [`Message::edns`] instead.
     pub additionals: Vec<Record>,
+    /// The EDNS(0) information of the message, if it carries an OPT pseudo-record.
+    pub edns: Option<Edns>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -45,16 +49,24 @@ pub enum MsgSection {
 // ctors
 impl Message {
     /// Creates a new [`Message`] containing a single [`Question`]
+    ///
+    /// The name is parsed beforehand, so that invalid names are reported rather than panicking:
+    /// ```
+    /// # use dirt::{dname::DomainName, message::Message, qtype::QType};
+    /// let domain_name: DomainName = "www.example.com".parse()?;
+    /// let query = Message::new_query(domain_name, QType::A, false, true);
+    /// assert_eq!(query.get_query().qtype, QType::A);
+    /// # Ok::<(), dirt::dname::Error>(())
+    /// ```
     pub fn new_query(
-        domain_name: &str,
+        domain_name: DomainName,
         record_type: QType,
         authoritative: bool,
         recursion_desired: bool,
     ) -> Self {
         let header = Header::gen_query_header(0, authoritative, recursion_desired).unwrap();
-        let name = DomainName::new(domain_name);
         let question = Question {
-            qname: name,
+            qname: domain_name,
             qclass: QClass::IN,
             qtype: record_type,
         };
@@ -65,12 +77,22 @@ impl Message {
             answers: vec![],
             authorities: vec![],
             additionals: vec![],
+            edns: None,
         }
     }
 }
 
 // querying data
 impl Message {
+    /// The full 12-bit RCODE of the message: the header's 4 bits,
+    /// extended by the upper 8 bits of the OPT pseudo-record, if there is one.
+    ///
+    /// See more in [RFC 6891 section 6.1.3](https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.3)
+    pub fn response_code(&self) -> u16 {
+        let upper = self.edns.as_ref().map_or(0, |edns| edns.extended_rcode);
+        u16::from(upper) << 4 | u16::from(u8::from(self.header.flags.response_code()))
+    }
+
     pub fn get_query(&self) -> &Question {
         self.questions
             .first()
@@ -86,13 +108,33 @@ impl Message {
     }
 
     pub fn get_record_by_type_from(&self, qtype: QType, section: MsgSection) -> Option<&Record> {
+        self.get_records_by_type_from(qtype, section).next()
+    }
+
+    /// Returns every record of the given type in the section, whatever its owner name
+    pub fn get_records_by_type_from(
+        &self,
+        qtype: QType,
+        section: MsgSection,
+    ) -> impl Iterator<Item = &Record> {
         self.get_records(section)
             .iter()
-            .find(|rec| rec.qtype == qtype)
+            .filter(move |rec| rec.qtype == qtype)
+    }
+
+    /// Returns the RRset of the given owner name and type in the section
+    pub fn get_rrset_from<'a>(
+        &'a self,
+        name: &'a DomainName,
+        qtype: QType,
+        section: MsgSection,
+    ) -> impl Iterator<Item = &'a Record> {
+        self.get_records_by_type_from(qtype, section)
+            .filter(move |rec| rec.name == *name)
     }
 }
 
-/// Wraps the errors that may be encountered during byte decoding of a [`Message`]
+/// Wraps the errors that may be encountered during byte decoding or encoding of a [`Message`]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Stores an error encountered while using [std::io] traits and structs
@@ -107,6 +149,9 @@ pub enum Error {
     /// Encountered during record parsing
     #[error(transparent)]
     Record(#[from] crate::record::Error),
+    /// Encountered during OPT pseudo-record parsing
+    #[error(transparent)]
+    Edns(#[from] crate::edns::Error),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,63 @@
+//! A borrowed, zero-copy view over the bytes of a message.
+//!
+//! [`MessageRef::parse`] checks the structure of the whole message once, without allocating.
+//! This validation pass is eager on purpose: it is what lets the section iterators yield
+//! questions and records rather than results, and stop only at the end of their section.
+//! The iteration itself is lazy: each question, record and name is located again as it is reached,
+//! and only decoded or converted to its owned counterpart when asked to.
+
+use crate::{header::Header, qclass::QClass, qtype::QType};
+
+/// A borrowed view over the bytes of a [`Message`](crate::message::Message)
+#[derive(Debug, Clone, Copy)]
+pub struct MessageRef<'a> {
+    pub(crate) msg: &'a [u8],
+    pub header: Header,
+    /// The offsets at which the question, answer, authority and additional sections start
+    pub(crate) sections: [usize; 4],
+}
+
+/// A borrowed view over a [`DomainName`](crate::dname::DomainName) within a message
+///
+/// The name's labels are only located when they are needed, following compression pointers.
+#[derive(Clone, Copy)]
+pub struct NameRef<'a> {
+    pub(crate) msg: &'a [u8],
+    pub(crate) offset: usize,
+}
+
+/// A borrowed view over a [`Question`](crate::question::Question) within a message
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionRef<'a> {
+    pub qname: NameRef<'a>,
+    pub qtype: QType,
+    pub qclass: QClass,
+}
+
+/// A borrowed view over a [`Record`](crate::record::Record) within a message
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    pub name: NameRef<'a>,
+    pub qtype: QType,
+    pub class: QClass,
+    pub time_to_live: u32,
+    /// The offset of the record's data within the message
+    pub(crate) rdata_offset: usize,
+    pub(crate) rdata: &'a [u8],
+}
+
+impl<'a> MessageRef<'a> {
+    /// The bytes of the whole message
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.msg
+    }
+}
+
+impl<'a> RecordRef<'a> {
+    /// The raw bytes of the record's data
+    ///
+    /// Domain names within the data may be compressed, see [`RecordRef::data`] to decode them.
+    pub fn rdata(&self) -> &'a [u8] {
+        self.rdata
+    }
+}
@@ -2,9 +2,12 @@
 ///
 /// We use this enum is place of all CLASS _and_ QCLASS values, for code clarity's sake.
 /// > "every CLASS is a valid QCLASS" -- RFC 1035
+///
+/// Codes without a dedicated variant are kept as [`QClass::Unknown`], as required by
+/// [RFC 3597](https://datatracker.ietf.org/doc/html/rfc3597).
 #[allow(clippy::upper_case_acronyms)]
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Hash, num_enum::TryFromPrimitive, num_enum::IntoPrimitive,
+    Debug, Clone, Copy, PartialEq, Eq, Hash, num_enum::FromPrimitive, num_enum::IntoPrimitive,
 )]
 #[repr(u16)]
 pub enum QClass {
@@ -19,4 +22,17 @@ pub enum QClass {
     HS = 4,
     /// any class (denoted as "*" in RFC 1035)
     ANY = 255,
+    /// A class code without a dedicated variant
+    #[num_enum(catch_all)]
+    Unknown(u16),
+}
+
+impl std::fmt::Display for QClass {
+    /// Writes the class's mnemonic, or its `CLASS<code>` form if it has none
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QClass::Unknown(code) => write!(f, "CLASS{code}"),
+            known => write!(f, "{known:?}"),
+        }
+    }
 }
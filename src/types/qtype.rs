@@ -2,9 +2,12 @@
 ///
 /// We use this enum is place of all TYPE _and_ QTYPE values, for code clarity's sake.
 /// > "all TYPEs are valid QTYPEs" -- RFC 1035
+///
+/// Codes without a dedicated variant are kept as [`QType::Unknown`], as required by
+/// [RFC 3597](https://datatracker.ietf.org/doc/html/rfc3597).
 #[allow(clippy::upper_case_acronyms)]
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Hash, num_enum::TryFromPrimitive, num_enum::IntoPrimitive,
+    Debug, Clone, Copy, PartialEq, Eq, Hash, num_enum::FromPrimitive, num_enum::IntoPrimitive,
 )]
 #[repr(u16)]
 pub enum QType {
@@ -49,6 +52,8 @@ pub enum QType {
     TXT = 16,
     /// an IPv6 host address (see RFC 3596)
     AAAA = 28,
+    /// an EDNS(0) pseudo-record (see RFC 6891)
+    OPT = 41,
     // QTYPEs below
     /// A request for a transfer of an entire zone
     AXFR = 252,
@@ -60,4 +65,17 @@ pub enum QType {
     MAILA = 254,
     /// A request for all records (denoted as "*" in RFC 1035)
     ANY = 255,
+    /// A type code without a dedicated variant
+    #[num_enum(catch_all)]
+    Unknown(u16),
+}
+
+impl std::fmt::Display for QType {
+    /// Writes the type's mnemonic, or its `TYPE<code>` form if it has none
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QType::Unknown(code) => write!(f, "TYPE{code}"),
+            known => write!(f, "{known:?}"),
+        }
+    }
 }
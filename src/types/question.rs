@@ -68,10 +68,4 @@ pub enum Error {
     /// Stores an error encountered while parsing the [DomainName]
     #[error(transparent)]
     Name(#[from] crate::dname::Error),
-    /// Stores an error encountered while parsin the [QType]
-    #[error("Failed to convert primitive to QType: {0}")]
-    Type(#[from] num_enum::TryFromPrimitiveError<QType>),
-    /// Stores an error encountered while parsin the [QClass]
-    #[error("Failed to convert primitive to QClass: {0}")]
-    Class(#[from] num_enum::TryFromPrimitiveError<QClass>),
 }
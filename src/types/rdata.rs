@@ -0,0 +1,180 @@
+//! The RDATA field of a resource record describes the resource.
+//! Its format varies according to the TYPE and CLASS of the resource record.
+//!
+//! See more in [RFC 1035 section 3.3](https://datatracker.ietf.org/doc/html/rfc1035#section-3.3)
+//! and [RFC 3596 section 2.2](https://datatracker.ietf.org/doc/html/rfc3596#section-2.2)
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{dname::DomainName, qtype::QType};
+
+/// The decoded data of a resource record
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RData {
+    /// a 32 bit Internet address
+    A(Ipv4Addr),
+    /// a 128 bit IPv6 address
+    AAAA(Ipv6Addr),
+    /// a host which should be authoritative for the specified class and domain
+    NS(DomainName),
+    /// the canonical or primary name for the owner. The owner name is an alias.
+    CNAME(DomainName),
+    /// a pointer to some location in the domain name space
+    PTR(DomainName),
+    /// the start of a zone of authority
+    SOA(Soa),
+    /// a host willing to act as a mail exchange for the owner name
+    MX {
+        /// the preference given to this RR among others at the same owner. Lower values are preferred.
+        preference: u16,
+        /// a host willing to act as a mail exchange for the owner name
+        exchange: DomainName,
+    },
+    /// one or more character strings
+    TXT(Vec<Vec<u8>>),
+    /// host information
+    HINFO {
+        /// the CPU type
+        cpu: Vec<u8>,
+        /// the operating system type
+        os: Vec<u8>,
+    },
+    /// anything at all, as long as it is 65535 octets or less
+    NULL(Vec<u8>),
+    /// the raw data of a record type without a dedicated representation
+    Unknown(Vec<u8>),
+}
+
+/// The data of an SOA record, marking the start of a zone of authority
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Soa {
+    /// the name server that was the original or primary source of data for this zone
+    pub mname: DomainName,
+    /// the mailbox of the person responsible for this zone
+    pub rname: DomainName,
+    /// the version number of the original copy of the zone
+    pub serial: u32,
+    /// time interval (in seconds) before the zone should be refreshed
+    pub refresh: u32,
+    /// time interval (in seconds) that should elapse before a failed refresh should be retried
+    pub retry: u32,
+    /// time value (in seconds) that specifies the upper limit on the time interval
+    /// that can elapse before the zone is no longer authoritative
+    pub expire: u32,
+    /// the minimum TTL field that should be exported with any RR from this zone
+    pub minimum: u32,
+}
+
+impl RData {
+    /// The record type this data belongs to, if it has a dedicated representation
+    pub fn qtype(&self) -> Option<QType> {
+        match self {
+            RData::A(_) => Some(QType::A),
+            RData::AAAA(_) => Some(QType::AAAA),
+            RData::NS(_) => Some(QType::NS),
+            RData::CNAME(_) => Some(QType::CNAME),
+            RData::PTR(_) => Some(QType::PTR),
+            RData::SOA(_) => Some(QType::SOA),
+            RData::MX { .. } => Some(QType::MX),
+            RData::TXT(_) => Some(QType::TXT),
+            RData::HINFO { .. } => Some(QType::HINFO),
+            RData::NULL(_) => Some(QType::NULL),
+            RData::Unknown(_) => None,
+        }
+    }
+
+    /// Returns the address held by A and AAAA data
+    pub fn as_ip_addr(&self) -> Option<IpAddr> {
+        match self {
+            RData::A(addr) => Some(IpAddr::V4(*addr)),
+            RData::AAAA(addr) => Some(IpAddr::V6(*addr)),
+            _ => None,
+        }
+    }
+
+    /// Returns the domain name held by NS, CNAME and PTR data
+    pub fn as_domain_name(&self) -> Option<&DomainName> {
+        match self {
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a character string in its quoted presentation form
+fn fmt_char_string(f: &mut std::fmt::Formatter<'_>, string: &[u8]) -> std::fmt::Result {
+    f.write_str("\"")?;
+    for &byte in string {
+        match byte {
+            b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+            0x20..=0x7E => write!(f, "{}", byte as char)?,
+            _ => write!(f, "\\{byte:03}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl std::fmt::Display for RData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RData::A(addr) => write!(f, "{addr}"),
+            RData::AAAA(addr) => write!(f, "{addr}"),
+            RData::NS(name) | RData::CNAME(name) | RData::PTR(name) => write!(f, "{name}"),
+            RData::SOA(soa) => write!(
+                f,
+                "{} {} {} {} {} {} {}",
+                soa.mname, soa.rname, soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum
+            ),
+            RData::MX {
+                preference,
+                exchange,
+            } => write!(f, "{preference} {exchange}"),
+            RData::TXT(strings) => {
+                for (idx, string) in strings.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(" ")?;
+                    }
+                    fmt_char_string(f, string)?;
+                }
+                Ok(())
+            }
+            RData::HINFO { cpu, os } => {
+                fmt_char_string(f, cpu)?;
+                f.write_str(" ")?;
+                fmt_char_string(f, os)
+            }
+            // RFC 3597 generic encoding
+            RData::NULL(data) | RData::Unknown(data) => {
+                write!(f, "\\# {}", data.len())?;
+                if !data.is_empty() {
+                    f.write_str(" ")?;
+                }
+                data.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }
+    }
+}
+
+pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+/// Wraps the errors that may be encountered during byte decoding or encoding of [`RData`]
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Stores an error encountered while using [std::io] traits and structs
+    #[error("Failed to parse record data: {0}")]
+    Io(#[from] std::io::Error),
+    /// Stores an error encountered while parsing a [DomainName] within the data
+    #[error(transparent)]
+    Name(#[from] crate::dname::Error),
+    /// The decoded data did not span exactly the declared data length
+    #[error("Record data of type {qtype:?} declared {expected} octets, but {actual} were decoded")]
+    Length {
+        qtype: QType,
+        expected: u16,
+        actual: u64,
+    },
+    /// A character string to encode was longer than its single length octet can count
+    #[error("Character string of {size} octets exceeds the maximum of 255 octets")]
+    CharStringTooLong { size: usize },
+}
@@ -1,4 +1,4 @@
-use crate::{dname::DomainName, qclass::QClass, qtype::QType};
+use crate::{dname::DomainName, qclass::QClass, qtype::QType, rdata::RData};
 
 /// A resource record
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -16,31 +16,12 @@ pub struct Record {
     /// a variable length string of octets that describes the resource. The format of this information varies according to the TYPE and CLASS of the resource record.
     ///
     /// For example, the if the TYPE is A and the CLASS is IN, the RDATA field is a 4 octet ARPA Internet address.
-    pub rdata: Vec<u8>,
-}
-
-// parsing data
-impl Record {
-    pub fn data_as_str(&self) -> &str {
-        std::str::from_utf8(&self.rdata).unwrap()
-    }
-
-    pub fn data_as_ip_addr(&self) -> std::net::IpAddr {
-        match self.qtype {
-            QType::A => std::net::IpAddr::V4(std::net::Ipv4Addr::from(
-                <[u8; 4]>::try_from(&self.rdata[..4]).unwrap(),
-            )),
-            QType::AAAA => std::net::IpAddr::V6(std::net::Ipv6Addr::from(
-                <[u8; 16]>::try_from(&self.rdata[..16]).unwrap(),
-            )),
-            _ => unreachable!(),
-        }
-    }
+    pub rdata: RData,
 }
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
 
-/// Wraps the errors that may be encountered during byte decoding of a [`Record`]
+/// Wraps the errors that may be encountered during byte decoding or encoding of a [`Record`]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Stores an error encountered while using [std::io] traits and structs
@@ -49,10 +30,13 @@ pub enum Error {
     /// Stores an error encountered while parsing the [DomainName]
     #[error(transparent)]
     Name(#[from] crate::dname::Error),
-    /// Stores an error encountered while parsin the [QType]
-    #[error("Failed to convert primitive to QType: {0}")]
-    Type(#[from] num_enum::TryFromPrimitiveError<QType>),
-    /// Stores an error encountered while parsin the [QClass]
-    #[error("Failed to convert primitive to QClass: {0}")]
-    Class(#[from] num_enum::TryFromPrimitiveError<QClass>),
+    /// Stores an error encountered while parsing the [RData]
+    #[error(transparent)]
+    Data(#[from] crate::rdata::Error),
+    /// The encoded [RData] was longer than the 16-bit data length can count
+    #[error(
+        "Record data of {size} octets exceeds the maximum of {} octets",
+        u16::MAX
+    )]
+    DataTooLong { size: usize },
 }